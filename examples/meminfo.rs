@@ -63,7 +63,7 @@ fn read_meminfo() -> Option<MemInfo> {
 fn draw_chart(table: &mut InMemoryTable<MemInfo>) -> Result<()> {
     let start_time = table.first()?.0;
     let end_time = table.last()?.0;
-    let mem_total = table.last()?.1.total;
+    let mem_total = table.last()?.1.map(|v| v.total).unwrap_or_default();
 
     let root = SVGBackend::new("meminfo.svg", (1024, 768)).into_drawing_area();
     root.fill(&WHITE)?;
@@ -80,19 +80,22 @@ fn draw_chart(table: &mut InMemoryTable<MemInfo>) -> Result<()> {
     chart
         .configure_series_labels()
         .position(SeriesLabelPosition::UpperRight)
-        .background_style(&RGBColor(160, 160, 160))
+        .background_style(RGBColor(160, 160, 160))
         .draw()?;
 
     let mut draw_line = |label, color| -> Result<()> {
         chart
             .draw_series(LineSeries::new(
-                table.iter()?.map(|(t, v)| match label {
-                    "MemFree" => (t, v.free),
-                    "MemAvailable" => (t, v.avail),
-                    "Buffers" => (t, v.buffers),
-                    "Cached" => (t, v.cached),
-                    _ => (t, v.total),
-                }),
+                table
+                    .iter()?
+                    .filter_map(|(t, v)| Some((t, v?)))
+                    .map(|(t, v)| match label {
+                        "MemFree" => (t, v.free),
+                        "MemAvailable" => (t, v.avail),
+                        "Buffers" => (t, v.buffers),
+                        "Cached" => (t, v.cached),
+                        _ => (t, v.total),
+                    }),
                 color,
             ))?
             .label(label)
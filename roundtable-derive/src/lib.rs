@@ -0,0 +1,296 @@
+//! `#[derive(DataPoint)]` for roundtable, enabled through the `derive`
+//! feature of the `roundtable` crate.
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, Data, DataEnum, DeriveInput, Fields, Ident, Index, LitStr,
+    Member, Type,
+};
+
+struct Field {
+    member: Member,
+    ty: Type,
+    name: String,
+    skip: bool,
+    source: Option<TokenStream2>,
+}
+
+/// Implements `DataPoint`, `Aggregate` and `Rate` for a struct with the same
+/// on-disk layout as the `datapoint!` macro. For enums without fields it
+/// implements `Discrete` instead, which requires `Copy` and `Default`.
+///
+/// Fields accept `#[datapoint(...)]` attributes:
+///
+/// - `skip` leaves the field out of the stored data point. It keeps its
+///   default value in data points read from a table.
+/// - `rename = "name"` stores the field under another name in the schema,
+///   so that renaming a field does not change the fingerprint.
+/// - `source = "gauge"` (or `counter`, `derive`, `absolute`) overrides the
+///   data source kind of the table for this field.
+#[proc_macro_derive(DataPoint, attributes(datapoint))]
+pub fn derive_data_point(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(|e| e.to_compile_error())
+        .into()
+}
+
+fn parse_fields(input: &DeriveInput) -> syn::Result<Vec<Field>> {
+    let fields = match &input.data {
+        Data::Struct(s) => &s.fields,
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "DataPoint can only be derived for structs and enums",
+            ))
+        }
+    };
+
+    let fields = match fields {
+        Fields::Named(f) => f.named.iter().collect(),
+        Fields::Unnamed(f) => f.unnamed.iter().collect(),
+        Fields::Unit => vec![],
+    };
+
+    let mut res = vec![];
+
+    for (i, f) in fields.into_iter().enumerate() {
+        let (member, name) = match &f.ident {
+            Some(ident) => (Member::Named(ident.clone()), ident.to_string()),
+            None => (Member::Unnamed(Index::from(i)), i.to_string()),
+        };
+        let mut field = Field {
+            member,
+            ty: f.ty.clone(),
+            name,
+            skip: false,
+            source: None,
+        };
+
+        for attr in f.attrs.iter().filter(|a| a.path().is_ident("datapoint")) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("skip") {
+                    field.skip = true;
+                    return Ok(());
+                }
+
+                if meta.path.is_ident("rename") {
+                    let name: LitStr = meta.value()?.parse()?;
+                    field.name = name.value();
+                    return Ok(());
+                }
+
+                if meta.path.is_ident("source") {
+                    let kind: LitStr = meta.value()?.parse()?;
+                    let variant = match kind.value().as_str() {
+                        "gauge" => quote!(Gauge),
+                        "counter" => quote!(Counter),
+                        "derive" => quote!(Derive),
+                        "absolute" => quote!(Absolute),
+                        _ => return Err(meta.error("expected gauge, counter, derive or absolute")),
+                    };
+                    field.source = Some(quote!(::roundtable::options::SourceKind::#variant));
+                    return Ok(());
+                }
+
+                Err(meta.error("unknown datapoint attribute"))
+            })?;
+        }
+
+        res.push(field);
+    }
+
+    Ok(res)
+}
+
+fn expand_enum(input: &DeriveInput, data: &DataEnum) -> syn::Result<TokenStream2> {
+    for v in data.variants.iter() {
+        if !matches!(v.fields, Fields::Unit) {
+            return Err(syn::Error::new_spanned(
+                v,
+                "DataPoint can only be derived for enums without fields",
+            ));
+        }
+    }
+
+    let ident = &input.ident;
+    let type_name = ident.to_string();
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let variants: Vec<&Ident> = data.variants.iter().map(|v| &v.ident).collect();
+    let names: Vec<String> = variants.iter().map(|v| v.to_string()).collect();
+    let indices: Vec<u32> = (0..variants.len() as u32).collect();
+    let count = variants.len();
+
+    Ok(quote! {
+        impl #impl_generics ::roundtable::data::Discrete for #ident #ty_generics #where_clause {
+            const NAME: &'static str = #type_name;
+            const VARIANTS: &'static [&'static str] = &[#(#names),*];
+            type Weights = [u64; #count];
+
+            fn new_weights() -> Self::Weights {
+                [0; #count]
+            }
+
+            fn to_index(&self) -> u32 {
+                match self {
+                    #(Self::#variants => #indices,)*
+                }
+            }
+
+            fn from_index(index: u32) -> Option<Self> {
+                match index {
+                    #(#indices => Some(Self::#variants),)*
+                    _ => None,
+                }
+            }
+        }
+    })
+}
+
+fn expand(input: DeriveInput) -> syn::Result<TokenStream2> {
+    if let Data::Enum(data) = &input.data {
+        return expand_enum(&input, data);
+    }
+
+    let fields = parse_fields(&input)?;
+    let stored: Vec<&Field> = fields.iter().filter(|f| !f.skip).collect();
+    let ident = &input.ident;
+    let type_name = ident.to_string();
+    let mut generics = input.generics.clone();
+    let where_clause = generics.make_where_clause();
+
+    for f in stored.iter() {
+        let ty = &f.ty;
+        where_clause.predicates.push(parse_quote! {
+            #ty: ::roundtable::data::DataPoint
+                + ::roundtable::data::Aggregate
+                + ::roundtable::data::Rate
+                + ::std::clone::Clone
+        });
+    }
+
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    let members: Vec<&Member> = stored.iter().map(|f| &f.member).collect();
+    let types: Vec<&Type> = stored.iter().map(|f| &f.ty).collect();
+    let names: Vec<&String> = stored.iter().map(|f| &f.name).collect();
+    let accs: Vec<_> = (0..stored.len())
+        .map(|i| format_ident!("__acc{}", i))
+        .collect();
+    let kinds: Vec<TokenStream2> = stored
+        .iter()
+        .map(|f| f.source.clone().unwrap_or(quote!(kind)))
+        .collect();
+
+    let acc_type = types.iter().rev().fold(
+        quote!(()),
+        |tail, ty| quote!((<#ty as ::roundtable::data::Aggregate>::Acc, #tail)),
+    );
+    let acc_new = types.iter().rev().fold(
+        quote!(()),
+        |tail, ty| quote!((<#ty as ::roundtable::data::Aggregate>::new_acc(), #tail)),
+    );
+    let acc_pat = accs
+        .iter()
+        .rev()
+        .fold(quote!(()), |tail, a| quote!((#a, #tail)));
+
+    Ok(quote! {
+        impl #impl_generics ::roundtable::data::DataPoint for #ident #ty_generics #where_clause {
+            fn get_size(&self) -> u64 {
+                0 #(+ ::roundtable::data::DataPoint::get_size(&self.#members))*
+            }
+
+            fn get_hash(&self) -> u64 {
+                1_u64 #(.wrapping_mul(::roundtable::data::DataPoint::get_hash(&self.#members)))*
+            }
+
+            fn get_schema(&self) -> ::roundtable::schema::Schema {
+                ::roundtable::schema::Schema::Struct(
+                    #type_name.to_string(),
+                    vec![
+                        #((
+                            #names.to_string(),
+                            ::roundtable::data::DataPoint::get_schema(&self.#members),
+                        ),)*
+                    ],
+                )
+            }
+
+            fn write_out<W: ::std::io::Write + ::std::io::Seek>(
+                &self,
+                writer: &mut W,
+            ) -> ::std::io::Result<()> {
+                #(::roundtable::data::DataPoint::write_out(&self.#members, writer)?;)*
+                Ok(())
+            }
+
+            fn read_in<R: ::std::io::Read + ::std::io::Seek>(
+                &mut self,
+                reader: &mut R,
+            ) -> ::std::io::Result<()> {
+                #(::roundtable::data::DataPoint::read_in(&mut self.#members, reader)?;)*
+                Ok(())
+            }
+
+            fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64) {
+                #(::roundtable::data::DataPoint::lerp(
+                    &mut self.#members,
+                    &v0.#members,
+                    &v1.#members,
+                    numer,
+                    denom,
+                );)*
+            }
+        }
+
+        impl #impl_generics ::roundtable::data::Aggregate for #ident #ty_generics #where_clause {
+            type Acc = #acc_type;
+
+            fn new_acc() -> Self::Acc {
+                #acc_new
+            }
+
+            fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+                let #acc_pat = acc;
+                #(::roundtable::data::Aggregate::accumulate(&self.#members, #accs, weight);)*
+            }
+
+            fn finish(
+                &mut self,
+                acc: &Self::Acc,
+                cf: ::roundtable::options::Consolidation,
+                weight: u64,
+            ) {
+                let #acc_pat = acc;
+                #(::roundtable::data::Aggregate::finish(&mut self.#members, #accs, cf, weight);)*
+            }
+
+            fn quantile(&mut self, values: &[Self], q: f64) {
+                #(
+                    let field: Vec<#types> = values.iter().map(|v| v.#members.clone()).collect();
+                    ::roundtable::data::Aggregate::quantile(&mut self.#members, &field, q);
+                )*
+            }
+        }
+
+        impl #impl_generics ::roundtable::data::Rate for #ident #ty_generics #where_clause {
+            fn rate(
+                &mut self,
+                prev: &Self,
+                cur: &Self,
+                kind: ::roundtable::options::SourceKind,
+                dt: u64,
+            ) {
+                #(::roundtable::data::Rate::rate(
+                    &mut self.#members,
+                    &prev.#members,
+                    &cur.#members,
+                    #kinds,
+                    dt,
+                );)*
+            }
+        }
+    })
+}
@@ -0,0 +1,138 @@
+use roundtable as rt;
+use roundtable::prelude::*;
+
+roundtable::datapoint! {
+    struct Link {
+        rx: u64,
+        tx: u64,
+    }
+
+    struct Host {
+        load: [f32; 3],
+        link: Link,
+    }
+}
+
+mod derived {
+    use super::*;
+
+    /// Same layout as the `datapoint!` version.
+    #[derive(Clone, Copy, Debug, Default, PartialEq, DataPoint)]
+    pub struct Link {
+        /// Received bytes.
+        pub rx: u64,
+        /// Transmitted bytes.
+        pub tx: u64,
+    }
+
+    #[derive(Clone, Copy, Debug, Default, PartialEq, DataPoint)]
+    pub struct Host {
+        pub load: [f32; 3],
+        pub link: Link,
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, DataPoint)]
+struct Pair<T>(T, T);
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, DataPoint)]
+struct Counters {
+    #[datapoint(rename = "rx")]
+    received: u64,
+    #[datapoint(skip)]
+    scratch: u32,
+    #[datapoint(source = "gauge")]
+    queue: u64,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, DataPoint)]
+enum Stage {
+    #[default]
+    Idle,
+    Busy = 4,
+    Failed,
+}
+
+#[test]
+fn same_layout_as_macro() {
+    let host = Host {
+        load: [0.5, 1.0, 1.5],
+        link: Link { rx: 1, tx: 2 },
+    };
+    let derived = derived::Host {
+        load: [0.5, 1.0, 1.5],
+        link: derived::Link { rx: 1, tx: 2 },
+    };
+
+    assert_eq!(derived.get_size(), host.get_size());
+    assert_eq!(derived.get_hash(), host.get_hash());
+    assert_eq!(derived.get_schema(), host.get_schema());
+    assert_eq!(derived.get_fingerprint(), host.get_fingerprint());
+
+    let opts = Options::new(0, 10, 100);
+    let mut t = rt::create::in_memory(opts, host).unwrap();
+    t.insert(10, &host).unwrap();
+    let buf = t.into_inner().into_inner();
+    let mut t = rt::load::from_buffer::<derived::Host, _>(opts, buf).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&derived));
+}
+
+#[test]
+fn generic_tuple_struct() {
+    let schema = Pair::<u16>::default().get_schema();
+    assert_eq!(schema.to_string(), "Pair { 0: u16, 1: u16 }");
+    assert_eq!(Pair(1_u8, 2_u8).get_size(), 2);
+
+    let opts = Options::new(0, 10, 100).pending(Consolidation::Max);
+    let mut t = rt::create::in_memory(opts, Pair(0.0, 0.0)).unwrap();
+    t.insert(10, &Pair(1.0, 2.0)).unwrap();
+    t.insert(15, &Pair(3.0, 4.0)).unwrap();
+    t.insert(20, &Pair(0.0, 0.0)).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&Pair(3.0, 4.0)));
+}
+
+#[test]
+fn field_attributes() {
+    let schema = Counters::default().get_schema();
+    assert_eq!(schema.to_string(), "Counters { rx: u64, queue: u64 }");
+    assert_eq!(Counters::default().get_size(), 16);
+
+    let opts = Options::new(0, 10, 100).source(SourceKind::Counter);
+    let mut t = rt::create::in_memory(opts, Counters::default()).unwrap();
+    let dp = |received, queue| Counters {
+        received,
+        scratch: 7,
+        queue,
+    };
+    t.insert(10, &dp(100, 5)).unwrap();
+    t.insert(20, &dp(300, 8)).unwrap();
+    assert_eq!(
+        t.get(20).unwrap(),
+        Some(&Counters {
+            received: 20,
+            scratch: 0,
+            queue: 8,
+        })
+    );
+}
+
+#[test]
+fn enums() {
+    roundtable::datapoint! {
+        enum Phase {
+            Idle,
+            Busy,
+            Failed,
+        }
+    }
+
+    assert_eq!(Stage::Busy.get_fingerprint(), Phase::Busy.get_fingerprint());
+    assert_eq!(Stage::Busy.get_hash(), Phase::Busy.get_hash());
+
+    let opts = Options::new(0, 10, 100);
+    let mut t = rt::create::in_memory(opts, Phase::Idle).unwrap();
+    t.insert(10, &Phase::Failed).unwrap();
+    let buf = t.into_inner().into_inner();
+    let mut t = rt::load::from_buffer::<Stage, _>(opts, buf).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&Stage::Failed));
+}
@@ -1,17 +1,19 @@
 use super::error::Error;
+use super::load;
 use super::prelude::*;
 use super::Result;
 use std::fs::{File, OpenOptions};
-use std::io::Cursor;
+use std::io::{Cursor, ErrorKind};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
 
-pub fn in_memory<T: DataPoint + Copy + Default>(
+pub fn in_memory<T: DataPoint + Aggregate + Rate + Clone + Default>(
     opts: Options,
     first_dp: T,
 ) -> Result<Table<T, Cursor<Vec<u8>>>> {
     let data = if opts.preallocate {
-        let len = usize::try_from(first_dp.get_size() * opts.dp_count())
-            .map_err(|_| Error::IntConvError)?;
+        let len = Table::<T, Cursor<Vec<u8>>>::full_len(&opts, &first_dp)?;
+        let len = usize::try_from(len).map_err(|_| Error::IntConvError)?;
         Cursor::new(vec![0; len])
     } else {
         Cursor::new(vec![])
@@ -20,7 +22,7 @@ pub fn in_memory<T: DataPoint + Copy + Default>(
     Table::new(&opts, &first_dp, data)
 }
 
-pub fn in_file<T: DataPoint + Copy + Default, P: AsRef<Path>>(
+pub fn in_file<T: DataPoint + Aggregate + Rate + Clone + Default, P: AsRef<Path>>(
     opts: Options,
     first_dp: T,
     path: P,
@@ -42,9 +44,52 @@ pub fn in_file<T: DataPoint + Copy + Default, P: AsRef<Path>>(
     .map_err(Error::IoError)?;
 
     if opts.preallocate {
-        let len = first_dp.get_size() * opts.dp_count();
+        let len = Table::<T, File>::full_len(&opts, &first_dp)?;
         file.set_len(len).map_err(Error::IoError)?;
     }
 
-    Table::new(&opts, &first_dp, file)
+    let mut table = Table::new(&opts, &first_dp, file)?;
+    table.set_sync(|f| f.sync_data());
+    Ok(table)
+}
+
+/// Opens the table at `path`, or creates it with `first_dp` as its first
+/// data point if there is no file. A new table is written to a temporary
+/// file next to `path` and linked into place, so that other processes never
+/// see a partly written table and an existing file is never replaced;
+/// `Options::overwrite` is ignored. An existing table must match `opts` as
+/// checked by `Table::check_options` and store data points of type `T`.
+pub fn open_or_create<T: DataPoint + Aggregate + Rate + Clone + Default, P: AsRef<Path>>(
+    opts: Options,
+    first_dp: T,
+    path: P,
+) -> Result<Table<T, File>> {
+    let path = path.as_ref();
+
+    if path.exists() {
+        return open_existing(opts, path);
+    }
+
+    static COUNT: AtomicU64 = AtomicU64::new(0);
+    let n = COUNT.fetch_add(1, Ordering::Relaxed);
+    let tmp = path.with_extension(format!("create-{}-{}", std::process::id(), n));
+    let created = in_file(opts.overwrite(true), first_dp, &tmp)
+        .and_then(|t| t.into_inner().sync_all().map_err(Error::IoError));
+    let linked = created.and_then(|_| std::fs::hard_link(&tmp, path).map_err(Error::IoError));
+    _ = std::fs::remove_file(&tmp);
+
+    match linked {
+        Ok(()) => load::from_file(opts, path),
+        Err(Error::IoError(e)) if e.kind() == ErrorKind::AlreadyExists => open_existing(opts, path),
+        Err(e) => Err(e),
+    }
+}
+
+fn open_existing<T: DataPoint + Aggregate + Rate + Clone + Default>(
+    opts: Options,
+    path: &Path,
+) -> Result<Table<T, File>> {
+    let table = load::from_file(opts, path)?;
+    table.check_options(&opts)?;
+    Ok(table)
 }
@@ -1,20 +1,195 @@
+use super::options::{Consolidation, SourceKind};
+use super::schema::{Primitive, Schema};
 use std::io::{Read, Seek, Write};
 
 pub trait DataPoint {
     fn get_size(&self) -> u64;
     fn get_hash(&self) -> u64;
+    fn get_schema(&self) -> Schema;
+
+    /// Hash of the schema that, unlike `get_hash`, changes when fields are
+    /// renamed, reordered or nested differently.
+    fn get_fingerprint(&self) -> u64 {
+        self.get_schema().fingerprint()
+    }
     fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()>;
     fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()>;
     fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64);
+
+    /// Returns a data point with the layout of `self` and every value zero.
+    /// Only data points whose layout is chosen at runtime need to override
+    /// it.
+    fn zeroed(&self) -> Self
+    where
+        Self: Default,
+    {
+        Self::default()
+    }
+}
+
+/// Combines any number of data points into one, field by field.
+///
+/// Values are folded into an accumulator with `accumulate`, partial
+/// accumulators can be combined with `merge`, and `finish` turns the result
+/// into a data point according to the requested consolidation function.
+/// Sums are kept in a wide type (`i128`, `u128` or `f64`) so that averaging
+/// many small integers does not overflow.
+pub trait Aggregate {
+    type Acc: Accumulator;
+
+    fn new_acc() -> Self::Acc;
+    fn accumulate(&self, acc: &mut Self::Acc, weight: u64);
+    fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, weight: u64);
+
+    /// Sets each field to the `q`-quantile (nearest rank) of that field in
+    /// `values`, where `q` is between 0 and 1.
+    fn quantile(&mut self, values: &[Self], q: f64)
+    where
+        Self: Sized;
+
+    fn merge(acc: &mut Self::Acc, other: &Self::Acc) {
+        acc.merge(other);
+    }
+
+    /// Returns an empty accumulator for data points with the layout of
+    /// `self`, see `DataPoint::zeroed`.
+    fn new_acc_like(&self) -> Self::Acc {
+        Self::new_acc()
+    }
+}
+
+/// Turns successive raw readings of a data source into per-second rates.
+pub trait Rate {
+    /// Sets each field to the rate between the raw readings `prev` and `cur`
+    /// taken `dt` seconds apart, interpreted according to `kind`.
+    fn rate(&mut self, prev: &Self, cur: &Self, kind: SourceKind, dt: u64);
+}
+
+pub trait Accumulator: Clone + std::fmt::Debug {
+    fn merge(&mut self, other: &Self);
+    fn get_size(&self) -> u64;
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()>;
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()>;
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Summary<T, W> {
+    pub sum: W,
+    pub min: T,
+    pub max: T,
+    pub last: T,
+}
+
+impl Accumulator for () {
+    fn merge(&mut self, _other: &Self) {}
+
+    fn get_size(&self) -> u64 {
+        0
+    }
+
+    fn write_out<W: Write + Seek>(&self, _writer: &mut W) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, _reader: &mut R) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<A: Accumulator, B: Accumulator> Accumulator for (A, B) {
+    fn merge(&mut self, other: &Self) {
+        self.0.merge(&other.0);
+        self.1.merge(&other.1);
+    }
+
+    fn get_size(&self) -> u64 {
+        self.0.get_size() + self.1.get_size()
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.0.write_out(writer)?;
+        self.1.write_out(writer)
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        self.0.read_in(reader)?;
+        self.1.read_in(reader)
+    }
+}
+
+impl<A: Accumulator, const N: usize> Accumulator for [A; N] {
+    fn merge(&mut self, other: &Self) {
+        for (i, a) in self.iter_mut().enumerate() {
+            a.merge(&other[i]);
+        }
+    }
+
+    fn get_size(&self) -> u64 {
+        self.iter().map(|a| a.get_size()).sum()
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        for a in self.iter() {
+            a.write_out(writer)?;
+        }
+        Ok(())
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        for a in self.iter_mut() {
+            a.read_in(reader)?;
+        }
+        Ok(())
+    }
 }
 
 #[macro_export]
 macro_rules! datapoint {
-    ($($vis:vis struct $name:ident $block:tt)*) => {
+    ($($item:tt)*) => {
         use std::io::{Read, Write, Seek};
-        $(
-            $crate::_internal_struct_impl!($vis, $name, $block);
-        )*
+        $crate::_internal_items!($($item)*);
+    };
+}
+
+#[macro_export]
+macro_rules! _internal_items {
+    () => {};
+    ($vis:vis struct $name:ident $block:tt $($rest:tt)*) => {
+        $crate::_internal_struct_impl!($vis, $name, $block);
+        $crate::_internal_items!($($rest)*);
+    };
+    ($vis:vis enum $name:ident $block:tt $($rest:tt)*) => {
+        $crate::_internal_enum_impl!($vis, $name, $block);
+        $crate::_internal_items!($($rest)*);
+    };
+}
+
+#[macro_export]
+macro_rules! _internal_enum_impl {
+    ($vis:vis, $name:ident, {$first:ident $(, $variant:ident)* $(,)?}) => {
+        #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            #[default]
+            $first,
+            $($variant,)*
+        }
+        impl $crate::data::Discrete for $name {
+            const NAME: &'static str = stringify!($name);
+            const VARIANTS: &'static [&'static str] = &[stringify!($first) $(, stringify!($variant))*];
+            type Weights = [u64; [stringify!($first) $(, stringify!($variant))*].len()];
+
+            fn new_weights() -> Self::Weights {
+                [0; [stringify!($first) $(, stringify!($variant))*].len()]
+            }
+
+            fn to_index(&self) -> u32 {
+                *self as u32
+            }
+
+            fn from_index(index: u32) -> Option<Self> {
+                [Self::$first $(, Self::$variant)*].get(index as usize).copied()
+            }
+        }
     };
 }
 
@@ -26,10 +201,17 @@ macro_rules! _internal_struct_impl {
         impl DataPoint for $name {
             $crate::_internal_impl_get_size!($block);
             $crate::_internal_impl_get_hash!($block);
+            $crate::_internal_impl_get_schema!($name, $block);
             $crate::_internal_impl_write_out!($block);
             $crate::_internal_impl_read_in!($block);
             $crate::_internal_impl_lerp!($block);
         }
+        impl $crate::data::Aggregate for $name {
+            $crate::_internal_impl_aggregate!($block);
+        }
+        impl $crate::data::Rate for $name {
+            $crate::_internal_impl_rate!($block);
+        }
     };
 }
 
@@ -57,6 +239,22 @@ macro_rules! _internal_impl_get_hash {
     };
 }
 
+#[macro_export]
+macro_rules! _internal_impl_get_schema {
+    ($name:ident, {$($field:ident : $type:ty,)*}) => {
+        fn get_schema(&self) -> $crate::schema::Schema {
+            $crate::schema::Schema::Struct(
+                stringify!($name).to_string(),
+                vec![
+                    $(
+                        (stringify!($field).to_string(), self.$field.get_schema()),
+                    )*
+                ],
+            )
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! _internal_impl_write_out {
     ({$($field:ident : $type:ty,)*}) => {
@@ -92,8 +290,87 @@ macro_rules! _internal_impl_lerp {
     };
 }
 
+#[macro_export]
+macro_rules! _internal_impl_aggregate {
+    ({$($field:ident : $type:ty,)*}) => {
+        type Acc = $crate::_internal_acc_type!($($type),*);
+
+        fn new_acc() -> Self::Acc {
+            $crate::_internal_acc_new!($($type),*)
+        }
+
+        fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+            let $crate::_internal_acc_pat!($($field),*) = acc;
+            $(
+                self.$field.accumulate($field, weight);
+            )*
+        }
+
+        fn finish(&mut self, acc: &Self::Acc, cf: $crate::options::Consolidation, weight: u64) {
+            let $crate::_internal_acc_pat!($($field),*) = acc;
+            $(
+                self.$field.finish($field, cf, weight);
+            )*
+        }
+
+        fn quantile(&mut self, values: &[Self], q: f64) {
+            $(
+                let field: Vec<$type> = values.iter().map(|v| v.$field).collect();
+                self.$field.quantile(&field, q);
+            )*
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! _internal_impl_rate {
+    ({$($field:ident : $type:ty,)*}) => {
+        fn rate(
+            &mut self,
+            prev: &Self,
+            cur: &Self,
+            kind: $crate::options::SourceKind,
+            dt: u64,
+        ) {
+            $(
+                self.$field.rate(&prev.$field, &cur.$field, kind, dt);
+            )*
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! _internal_acc_type {
+    () => { () };
+    ($head:ty $(, $tail:ty)*) => {
+        (
+            <$head as $crate::data::Aggregate>::Acc,
+            $crate::_internal_acc_type!($($tail),*)
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! _internal_acc_new {
+    () => { () };
+    ($head:ty $(, $tail:ty)*) => {
+        (
+            <$head as $crate::data::Aggregate>::new_acc(),
+            $crate::_internal_acc_new!($($tail),*)
+        )
+    };
+}
+
+#[macro_export]
+macro_rules! _internal_acc_pat {
+    () => { () };
+    ($head:ident $(, $tail:ident)*) => {
+        ($head, $crate::_internal_acc_pat!($($tail),*))
+    };
+}
+
 macro_rules! _internal_datapoint_impl {
-    ($impl_type:ty, $seed:literal) => {
+    ($impl_type:ty, $wide_type:tt, $seed:literal, $prim:ident) => {
         impl DataPoint for $impl_type {
             fn get_size(&self) -> u64 {
                 std::mem::size_of::<Self>() as u64
@@ -103,6 +380,10 @@ macro_rules! _internal_datapoint_impl {
                 $seed * 0x100000001b3
             }
 
+            fn get_schema(&self) -> Schema {
+                Schema::Primitive(Primitive::$prim)
+            }
+
             fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
                 writer.write_all(&self.to_le_bytes())
             }
@@ -126,53 +407,869 @@ macro_rules! _internal_datapoint_impl {
             }
         }
 
-        _internal_array_impl!($impl_type);
+        _internal_aggregate_impl!($impl_type, $wide_type);
+        _internal_rate_impl!($impl_type, $wide_type);
+    };
+}
+
+macro_rules! _internal_aggregate_impl {
+    ($impl_type:ty, f64) => {
+        impl Aggregate for $impl_type {
+            type Acc = Summary<$impl_type, f64>;
+
+            fn new_acc() -> Self::Acc {
+                Summary {
+                    sum: 0.0,
+                    min: <$impl_type>::MAX,
+                    max: <$impl_type>::MIN,
+                    last: 0.0,
+                }
+            }
+
+            fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+                acc.sum += *self as f64 * weight as f64;
+                acc.min = acc.min.min(*self);
+                acc.max = acc.max.max(*self);
+                acc.last = *self;
+            }
+
+            fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, weight: u64) {
+                use Consolidation::*;
+
+                *self = match cf {
+                    Average if weight > 0 => (acc.sum / weight as f64) as $impl_type,
+                    Average => 0.0,
+                    Min => acc.min,
+                    Max => acc.max,
+                    Last => acc.last,
+                    Sum => acc.sum as $impl_type,
+                }
+            }
+
+            _internal_quantile_impl!();
+        }
+
+        impl Accumulator for Summary<$impl_type, f64> {
+            fn merge(&mut self, other: &Self) {
+                self.sum += other.sum;
+                self.min = self.min.min(other.min);
+                self.max = self.max.max(other.max);
+                self.last = other.last;
+            }
+
+            _internal_summary_io!();
+        }
+    };
+    ($impl_type:ty, $wide_type:tt) => {
+        impl Aggregate for $impl_type {
+            type Acc = Summary<$impl_type, $wide_type>;
+
+            fn new_acc() -> Self::Acc {
+                Summary {
+                    sum: 0,
+                    min: <$impl_type>::MAX,
+                    max: <$impl_type>::MIN,
+                    last: 0,
+                }
+            }
+
+            fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+                let v = (*self as $wide_type).wrapping_mul(weight as $wide_type);
+                acc.sum = acc.sum.wrapping_add(v);
+                acc.min = acc.min.min(*self);
+                acc.max = acc.max.max(*self);
+                acc.last = *self;
+            }
+
+            fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, weight: u64) {
+                use Consolidation::*;
+                let lo = <$impl_type>::MIN as $wide_type;
+                let hi = <$impl_type>::MAX as $wide_type;
+
+                *self = match cf {
+                    Average if weight > 0 => (acc.sum / weight as $wide_type) as $impl_type,
+                    Average => 0,
+                    Min => acc.min,
+                    Max => acc.max,
+                    Last => acc.last,
+                    Sum => acc.sum.clamp(lo, hi) as $impl_type,
+                }
+            }
+
+            _internal_quantile_impl!();
+        }
+
+        impl Accumulator for Summary<$impl_type, $wide_type> {
+            fn merge(&mut self, other: &Self) {
+                self.sum = self.sum.wrapping_add(other.sum);
+                self.min = self.min.min(other.min);
+                self.max = self.max.max(other.max);
+                self.last = other.last;
+            }
+
+            _internal_summary_io!();
+        }
+    };
+}
+
+macro_rules! _internal_rate_impl {
+    ($impl_type:ty, f64) => {
+        impl Rate for $impl_type {
+            fn rate(&mut self, prev: &Self, cur: &Self, kind: SourceKind, dt: u64) {
+                use SourceKind::*;
+                let dt = dt.max(1) as $impl_type;
+
+                *self = match kind {
+                    Gauge => *cur,
+                    Counter => (*cur - *prev).max(0.0) / dt,
+                    Derive => (*cur - *prev) / dt,
+                    Absolute => *cur / dt,
+                }
+            }
+        }
+    };
+    ($impl_type:ty, $wide_type:tt) => {
+        impl Rate for $impl_type {
+            fn rate(&mut self, prev: &Self, cur: &Self, kind: SourceKind, dt: u64) {
+                if kind == SourceKind::Gauge {
+                    *self = *cur;
+                    return;
+                }
+
+                let bits = <$impl_type>::BITS;
+                let dt = dt.max(1);
+
+                *self = if <$impl_type>::MIN == 0 {
+                    let v = unsigned_rate(*prev as u128, *cur as u128, bits, kind);
+                    (v / dt as u128).min(<$impl_type>::MAX as u128) as $impl_type
+                } else {
+                    let v = signed_rate(*prev as i128, *cur as i128, bits, kind);
+                    let lo = <$impl_type>::MIN as i128;
+                    let hi = <$impl_type>::MAX as i128;
+                    (v / dt as i128).clamp(lo, hi) as $impl_type
+                };
+            }
+        }
+    };
+}
+
+/// Change of an unsigned integer of `bits` bits from `prev` to `cur`.
+/// Counters that went down are assumed to have wrapped at 32 bits, then at
+/// 64 bits, then at their own width; derives that went down are 0.
+fn unsigned_rate(prev: u128, cur: u128, bits: u32, kind: SourceKind) -> u128 {
+    use SourceKind::*;
+
+    match kind {
+        Gauge | Absolute => cur,
+        Counter | Derive if cur >= prev => cur - prev,
+        Derive => 0,
+        Counter => {
+            let gap = prev - cur;
+
+            match bits {
+                ..=32 => (1 << bits) - gap,
+                _ if gap <= 1 << 32 => (1 << 32) - gap,
+                _ if gap <= 1 << 64 => (1 << 64) - gap,
+                _ => cur.wrapping_sub(prev),
+            }
+        }
+    }
+}
+
+/// Change of a signed integer of `bits` bits from `prev` to `cur`, wrapping
+/// counters like `unsigned_rate` and saturating where `i128` overflows.
+fn signed_rate(prev: i128, cur: i128, bits: u32, kind: SourceKind) -> i128 {
+    use SourceKind::*;
+    let delta = cur.saturating_sub(prev);
+
+    match kind {
+        Gauge | Absolute => cur,
+        Counter if delta < 0 && bits <= 32 => delta + (1 << bits),
+        Counter if delta < 0 => {
+            let wrapped = delta + (1 << 32);
+
+            if wrapped >= 0 {
+                wrapped
+            } else if let Some(v) = delta.checked_add(1 << 64).filter(|v| *v >= 0) {
+                v
+            } else {
+                i128::try_from((cur as u128).wrapping_sub(prev as u128)).unwrap_or(i128::MAX)
+            }
+        }
+        Counter | Derive => delta,
+    }
+}
+
+macro_rules! _internal_quantile_impl {
+    () => {
+        fn quantile(&mut self, values: &[Self], q: f64) {
+            let mut sorted = values.to_vec();
+            sorted.sort_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal));
+            let rank = (q.clamp(0.0, 1.0) * sorted.len() as f64).ceil() as usize;
+
+            if let Some(v) = sorted.get(rank.saturating_sub(1)) {
+                *self = *v;
+            }
+        }
+    };
+}
+
+macro_rules! _internal_summary_io {
+    () => {
+        fn get_size(&self) -> u64 {
+            self.sum.get_size() + self.min.get_size() * 3
+        }
+
+        fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+            self.sum.write_out(writer)?;
+            self.min.write_out(writer)?;
+            self.max.write_out(writer)?;
+            self.last.write_out(writer)
+        }
+
+        fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+            self.sum.read_in(reader)?;
+            self.min.read_in(reader)?;
+            self.max.read_in(reader)?;
+            self.last.read_in(reader)
+        }
     };
 }
 
-macro_rules! _internal_array_impl {
-    ($impl_type:ty) => {
-        impl<const N: usize> DataPoint for [$impl_type; N] {
+_internal_datapoint_impl!(i8, i128, 1087, I8);
+_internal_datapoint_impl!(u8, u128, 3119, U8);
+_internal_datapoint_impl!(i16, i128, 4909, I16);
+_internal_datapoint_impl!(u16, u128, 6113, U16);
+_internal_datapoint_impl!(i32, i128, 8191, I32);
+_internal_datapoint_impl!(u32, u128, 18181, U32);
+_internal_datapoint_impl!(i64, i128, 21169, I64);
+_internal_datapoint_impl!(u64, u128, 37199, U64);
+_internal_datapoint_impl!(i128, i128, 60493, I128);
+_internal_datapoint_impl!(u128, u128, 93911, U128);
+_internal_datapoint_impl!(f32, f64, 131071, F32);
+_internal_datapoint_impl!(f64, f64, 524287, F64);
+
+/// Nearest of `v0` and `v1` at `numer / denom` of the way between them, for
+/// values that cannot be interpolated.
+fn nearest<T: Clone>(v0: &T, v1: &T, numer: u64, denom: u64) -> T {
+    if numer.saturating_mul(2) < denom {
+        v0.clone()
+    } else {
+        v1.clone()
+    }
+}
+
+impl DataPoint for bool {
+    fn get_size(&self) -> u64 {
+        1
+    }
+
+    fn get_hash(&self) -> u64 {
+        8388593_u64.wrapping_mul(0x100000001b3)
+    }
+
+    fn get_schema(&self) -> Schema {
+        Schema::Primitive(Primitive::Bool)
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&[u8::from(*self)])
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        let mut buf = [0];
+        reader.read_exact(&mut buf)?;
+        *self = buf[0] != 0;
+        Ok(())
+    }
+
+    fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64) {
+        *self = nearest(v0, v1, numer, denom);
+    }
+}
+
+/// Flags consolidate to whether they were set for most of the time
+/// (`Average`), all of it (`Min`) or any of it (`Max` and `Sum`).
+impl Aggregate for bool {
+    type Acc = Summary<bool, u64>;
+
+    fn new_acc() -> Self::Acc {
+        Summary {
+            sum: 0,
+            min: true,
+            max: false,
+            last: false,
+        }
+    }
+
+    fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+        if *self {
+            acc.sum = acc.sum.saturating_add(weight);
+        }
+
+        acc.min &= *self;
+        acc.max |= *self;
+        acc.last = *self;
+    }
+
+    fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, weight: u64) {
+        use Consolidation::*;
+
+        *self = match cf {
+            Average => weight > 0 && acc.sum.saturating_mul(2) >= weight,
+            Min => acc.min,
+            Max | Sum => acc.max,
+            Last => acc.last,
+        }
+    }
+
+    _internal_quantile_impl!();
+}
+
+impl Accumulator for Summary<bool, u64> {
+    fn merge(&mut self, other: &Self) {
+        self.sum = self.sum.saturating_add(other.sum);
+        self.min &= other.min;
+        self.max |= other.max;
+        self.last = other.last;
+    }
+
+    _internal_summary_io!();
+}
+
+/// Flags are not counters, so they keep the raw reading for every source
+/// kind.
+impl Rate for bool {
+    fn rate(&mut self, _prev: &Self, cur: &Self, _kind: SourceKind, _dt: u64) {
+        *self = *cur;
+    }
+}
+
+impl DataPoint for char {
+    fn get_size(&self) -> u64 {
+        4
+    }
+
+    fn get_hash(&self) -> u64 {
+        16777213_u64.wrapping_mul(0x100000001b3)
+    }
+
+    fn get_schema(&self) -> Schema {
+        Schema::Primitive(Primitive::Char)
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&u32::from(*self).to_le_bytes())
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        let mut buf = [0; 4];
+        reader.read_exact(&mut buf)?;
+        *self = char::from_u32(u32::from_le_bytes(buf))
+            .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid char"))?;
+        Ok(())
+    }
+
+    fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64) {
+        *self = nearest(v0, v1, numer, denom);
+    }
+}
+
+/// Characters have no average or sum, so `Average` and `Sum` consolidate to
+/// the last value.
+impl Aggregate for char {
+    type Acc = Summary<char, u64>;
+
+    fn new_acc() -> Self::Acc {
+        Summary {
+            sum: 0,
+            min: char::MAX,
+            max: '\0',
+            last: '\0',
+        }
+    }
+
+    fn accumulate(&self, acc: &mut Self::Acc, _weight: u64) {
+        acc.min = acc.min.min(*self);
+        acc.max = acc.max.max(*self);
+        acc.last = *self;
+    }
+
+    fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, _weight: u64) {
+        use Consolidation::*;
+
+        *self = match cf {
+            Min => acc.min,
+            Max => acc.max,
+            Average | Last | Sum => acc.last,
+        }
+    }
+
+    _internal_quantile_impl!();
+}
+
+impl Accumulator for Summary<char, u64> {
+    fn merge(&mut self, other: &Self) {
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.last = other.last;
+    }
+
+    _internal_summary_io!();
+}
+
+impl Rate for char {
+    fn rate(&mut self, _prev: &Self, cur: &Self, _kind: SourceKind, _dt: u64) {
+        *self = *cur;
+    }
+}
+
+impl<T: DataPoint, const N: usize> DataPoint for [T; N] {
+    fn get_size(&self) -> u64 {
+        self.iter().map(|i| i.get_size()).sum()
+    }
+
+    fn get_hash(&self) -> u64 {
+        self.iter().fold(1_u64, |a, i| a.wrapping_mul(i.get_hash()))
+    }
+
+    fn get_schema(&self) -> Schema {
+        let elem = match self.first() {
+            Some(i) => i.get_schema(),
+            None => Schema::Struct(String::new(), vec![]),
+        };
+        Schema::Array(Box::new(elem), N as u64)
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        for i in self.iter() {
+            i.write_out(writer)?;
+        }
+        Ok(())
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        for i in self.iter_mut() {
+            i.read_in(reader)?;
+        }
+        Ok(())
+    }
+
+    fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64) {
+        for (i, v) in self.iter_mut().enumerate() {
+            v.lerp(&v0[i], &v1[i], numer, denom);
+        }
+    }
+}
+
+impl<T: Aggregate + Clone, const N: usize> Aggregate for [T; N] {
+    type Acc = [T::Acc; N];
+
+    fn new_acc() -> Self::Acc {
+        std::array::from_fn(|_| T::new_acc())
+    }
+
+    fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+        for (i, v) in self.iter().enumerate() {
+            v.accumulate(&mut acc[i], weight);
+        }
+    }
+
+    fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, weight: u64) {
+        for (i, v) in self.iter_mut().enumerate() {
+            v.finish(&acc[i], cf, weight);
+        }
+    }
+
+    fn quantile(&mut self, values: &[Self], q: f64) {
+        for (i, v) in self.iter_mut().enumerate() {
+            let elem: Vec<T> = values.iter().map(|a| a[i].clone()).collect();
+            v.quantile(&elem, q);
+        }
+    }
+}
+
+impl<T: Rate, const N: usize> Rate for [T; N] {
+    fn rate(&mut self, prev: &Self, cur: &Self, kind: SourceKind, dt: u64) {
+        for (i, v) in self.iter_mut().enumerate() {
+            v.rate(&prev[i], &cur[i], kind, dt);
+        }
+    }
+}
+
+/// Accumulator of an `Option`, which only aggregates the values that are
+/// present and counts their total weight.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Presence<A> {
+    pub weight: u64,
+    pub last: bool,
+    pub acc: A,
+}
+
+impl<A: Accumulator> Accumulator for Presence<A> {
+    fn merge(&mut self, other: &Self) {
+        self.weight = self.weight.saturating_add(other.weight);
+        self.last = other.last;
+        self.acc.merge(&other.acc);
+    }
+
+    fn get_size(&self) -> u64 {
+        9 + self.acc.get_size()
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.weight.write_out(writer)?;
+        self.last.write_out(writer)?;
+        self.acc.write_out(writer)
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        self.weight.read_in(reader)?;
+        self.last.read_in(reader)?;
+        self.acc.read_in(reader)
+    }
+}
+
+/// Stored as a presence byte followed by the value, which is written as
+/// `T::default()` when absent so that every slot has the same size.
+impl<T: DataPoint + Default + Clone> DataPoint for Option<T> {
+    fn get_size(&self) -> u64 {
+        1 + T::default().get_size()
+    }
+
+    fn get_hash(&self) -> u64 {
+        true.get_hash().wrapping_mul(T::default().get_hash())
+    }
+
+    fn get_schema(&self) -> Schema {
+        Schema::Option(Box::new(T::default().get_schema()))
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.is_some().write_out(writer)?;
+
+        match self {
+            Some(v) => v.write_out(writer),
+            None => T::default().write_out(writer),
+        }
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        let mut some = false;
+        let mut v = self.take().unwrap_or_default();
+        some.read_in(reader)?;
+        v.read_in(reader)?;
+        *self = some.then_some(v);
+        Ok(())
+    }
+
+    fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64) {
+        match (v0, v1) {
+            (Some(a), Some(b)) => {
+                let mut v = self.take().unwrap_or_default();
+                v.lerp(a, b, numer, denom);
+                *self = Some(v);
+            }
+            _ => *self = nearest(v0, v1, numer, denom),
+        }
+    }
+}
+
+/// Absent values are left out of the consolidation, which is `None` if no
+/// value was present (or the last one was absent, for `Last`).
+impl<T: Aggregate + Default + Clone> Aggregate for Option<T> {
+    type Acc = Presence<T::Acc>;
+
+    fn new_acc() -> Self::Acc {
+        Presence {
+            weight: 0,
+            last: false,
+            acc: T::new_acc(),
+        }
+    }
+
+    fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+        acc.last = self.is_some();
+
+        if let Some(v) = self {
+            acc.weight = acc.weight.saturating_add(weight);
+            v.accumulate(&mut acc.acc, weight);
+        }
+    }
+
+    fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, _weight: u64) {
+        let present = match cf {
+            Consolidation::Last => acc.last,
+            _ => acc.weight > 0,
+        };
+
+        *self = present.then(|| {
+            let mut v = self.take().unwrap_or_default();
+            v.finish(&acc.acc, cf, acc.weight);
+            v
+        });
+    }
+
+    fn quantile(&mut self, values: &[Self], q: f64) {
+        let present: Vec<T> = values.iter().flatten().cloned().collect();
+
+        *self = (!present.is_empty()).then(|| {
+            let mut v = self.take().unwrap_or_default();
+            v.quantile(&present, q);
+            v
+        });
+    }
+}
+
+/// A rate needs both readings, so it is `None` unless both are present.
+impl<T: Rate + Default + Clone> Rate for Option<T> {
+    fn rate(&mut self, prev: &Self, cur: &Self, kind: SourceKind, dt: u64) {
+        *self = match (prev, cur, kind) {
+            (_, _, SourceKind::Gauge) => cur.clone(),
+            (Some(p), Some(c), _) => {
+                let mut v = self.take().unwrap_or_default();
+                v.rate(p, c, kind, dt);
+                Some(v)
+            }
+            _ => None,
+        }
+    }
+}
+
+macro_rules! _internal_tuple_impl {
+    ($($type:ident $acc:ident $idx:tt),+) => {
+        impl<$($type: DataPoint),+> DataPoint for ($($type,)+) {
             fn get_size(&self) -> u64 {
-                std::mem::size_of::<Self>() as u64
+                0 $(+ self.$idx.get_size())+
             }
 
             fn get_hash(&self) -> u64 {
-                self.iter().fold(1_u64, |a, i| a.wrapping_mul(i.get_hash()))
+                1_u64 $(.wrapping_mul(self.$idx.get_hash()))+
+            }
+
+            fn get_schema(&self) -> Schema {
+                Schema::Struct(
+                    String::new(),
+                    vec![$((stringify!($idx).to_string(), self.$idx.get_schema()),)+],
+                )
             }
 
             fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
-                for i in self.iter() {
-                    i.write_out(writer)?;
-                }
+                $(self.$idx.write_out(writer)?;)+
                 Ok(())
             }
 
             fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
-                for i in self.iter_mut() {
-                    i.read_in(reader)?;
-                }
+                $(self.$idx.read_in(reader)?;)+
                 Ok(())
             }
 
             fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64) {
-                for (i, v) in self.iter_mut().enumerate() {
-                    v.lerp(&v0[i], &v1[i], numer, denom);
-                }
+                $(self.$idx.lerp(&v0.$idx, &v1.$idx, numer, denom);)+
+            }
+        }
+
+        impl<$($type: Aggregate + Clone),+> Aggregate for ($($type,)+) {
+            type Acc = $crate::_internal_acc_type!($($type),+);
+
+            fn new_acc() -> Self::Acc {
+                $crate::_internal_acc_new!($($type),+)
+            }
+
+            fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+                let $crate::_internal_acc_pat!($($acc),+) = acc;
+                $(self.$idx.accumulate($acc, weight);)+
+            }
+
+            fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, weight: u64) {
+                let $crate::_internal_acc_pat!($($acc),+) = acc;
+                $(self.$idx.finish($acc, cf, weight);)+
+            }
+
+            fn quantile(&mut self, values: &[Self], q: f64) {
+                $(
+                    let elem: Vec<$type> = values.iter().map(|v| v.$idx.clone()).collect();
+                    self.$idx.quantile(&elem, q);
+                )+
+            }
+        }
+
+        impl<$($type: Rate),+> Rate for ($($type,)+) {
+            fn rate(&mut self, prev: &Self, cur: &Self, kind: SourceKind, dt: u64) {
+                $(self.$idx.rate(&prev.$idx, &cur.$idx, kind, dt);)+
             }
         }
     };
 }
 
-_internal_datapoint_impl!(i8, 1087);
-_internal_datapoint_impl!(u8, 3119);
-_internal_datapoint_impl!(i16, 4909);
-_internal_datapoint_impl!(u16, 6113);
-_internal_datapoint_impl!(i32, 8191);
-_internal_datapoint_impl!(u32, 18181);
-_internal_datapoint_impl!(i64, 21169);
-_internal_datapoint_impl!(u64, 37199);
-_internal_datapoint_impl!(i128, 60493);
-_internal_datapoint_impl!(u128, 93911);
-_internal_datapoint_impl!(f32, 131071);
-_internal_datapoint_impl!(f64, 524287);
+_internal_tuple_impl!(A a 0);
+_internal_tuple_impl!(A a 0, B b 1);
+_internal_tuple_impl!(A a 0, B b 1, C c 2);
+_internal_tuple_impl!(A a 0, B b 1, C c 2, D d 3);
+_internal_tuple_impl!(A a 0, B b 1, C c 2, D d 3, E e 4);
+_internal_tuple_impl!(A a 0, B b 1, C c 2, D d 3, E e 4, F f 5);
+_internal_tuple_impl!(A a 0, B b 1, C c 2, D d 3, E e 4, F f 5, G g 6);
+_internal_tuple_impl!(A a 0, B b 1, C c 2, D d 3, E e 4, F f 5, G g 6, H h 7);
+
+/// Data points with a fixed set of values, stored as the index of the value.
+/// Implemented by the enums that `datapoint!` generates, which makes them
+/// data points with nearest-neighbour `lerp`.
+///
+/// They consolidate to the value held for the longest time (`Average` and
+/// `Sum`), the first or last value in declaration order (`Min` and `Max`)
+/// or the most recent value (`Last`).
+pub trait Discrete: Copy + Default {
+    const NAME: &'static str;
+    const VARIANTS: &'static [&'static str];
+
+    /// Per-value weights of the accumulator, `[u64; VARIANTS.len()]`.
+    type Weights: Clone + std::fmt::Debug + AsRef<[u64]> + AsMut<[u64]>;
+
+    fn new_weights() -> Self::Weights;
+    fn to_index(&self) -> u32;
+    fn from_index(index: u32) -> Option<Self>;
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Tally<W> {
+    pub weights: W,
+    pub min: u32,
+    pub max: u32,
+    pub last: u32,
+}
+
+impl<W: AsRef<[u64]> + AsMut<[u64]>> Tally<W> {
+    pub(crate) fn add(&mut self, index: u32, weight: u64) {
+        if let Some(w) = self.weights.as_mut().get_mut(index as usize) {
+            *w = w.saturating_add(weight);
+        }
+
+        self.min = self.min.min(index);
+        self.max = self.max.max(index);
+        self.last = index;
+    }
+
+    /// Returns the index of the consolidated value.
+    pub(crate) fn pick(&self, cf: Consolidation) -> u32 {
+        use Consolidation::*;
+
+        match cf {
+            Average | Sum => {
+                let weights = self.weights.as_ref().iter().enumerate();
+                let held = weights.filter(|(_, w)| **w > 0);
+                let top = held.rev().max_by_key(|(_, w)| **w);
+                top.map_or(self.last, |(i, _)| i as u32)
+            }
+            Min => self.min,
+            Max => self.max,
+            Last => self.last,
+        }
+    }
+}
+
+impl<W: Clone + std::fmt::Debug + AsRef<[u64]> + AsMut<[u64]>> Accumulator for Tally<W> {
+    fn merge(&mut self, other: &Self) {
+        for (w, o) in self.weights.as_mut().iter_mut().zip(other.weights.as_ref()) {
+            *w = w.saturating_add(*o);
+        }
+
+        self.min = self.min.min(other.min);
+        self.max = self.max.max(other.max);
+        self.last = other.last;
+    }
+
+    fn get_size(&self) -> u64 {
+        self.weights.as_ref().len() as u64 * 8 + 12
+    }
+
+    fn write_out<W2: Write + Seek>(&self, writer: &mut W2) -> std::io::Result<()> {
+        for w in self.weights.as_ref() {
+            w.write_out(writer)?;
+        }
+
+        self.min.write_out(writer)?;
+        self.max.write_out(writer)?;
+        self.last.write_out(writer)
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        for w in self.weights.as_mut() {
+            w.read_in(reader)?;
+        }
+
+        self.min.read_in(reader)?;
+        self.max.read_in(reader)?;
+        self.last.read_in(reader)
+    }
+}
+
+impl<T: Discrete> DataPoint for T {
+    fn get_size(&self) -> u64 {
+        4
+    }
+
+    fn get_hash(&self) -> u64 {
+        self.get_schema().fingerprint()
+    }
+
+    fn get_schema(&self) -> Schema {
+        let variants = T::VARIANTS.iter().map(|v| v.to_string()).collect();
+        Schema::Enum(T::NAME.to_string(), variants)
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        self.to_index().write_out(writer)
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        let mut index = 0_u32;
+        index.read_in(reader)?;
+        *self = T::from_index(index).ok_or_else(|| {
+            std::io::Error::new(std::io::ErrorKind::InvalidData, "invalid enum index")
+        })?;
+        Ok(())
+    }
+
+    fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64) {
+        *self = nearest(v0, v1, numer, denom);
+    }
+}
+
+impl<T: Discrete> Aggregate for T {
+    type Acc = Tally<T::Weights>;
+
+    fn new_acc() -> Self::Acc {
+        Tally {
+            weights: T::new_weights(),
+            min: u32::MAX,
+            max: 0,
+            last: 0,
+        }
+    }
+
+    fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+        acc.add(self.to_index(), weight);
+    }
+
+    fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, _weight: u64) {
+        if let Some(v) = T::from_index(acc.pick(cf)) {
+            *self = v;
+        }
+    }
+
+    fn quantile(&mut self, values: &[Self], q: f64) {
+        let mut sorted: Vec<u32> = values.iter().map(|v| v.to_index()).collect();
+        sorted.sort_unstable();
+        let rank = (q.clamp(0.0, 1.0) * sorted.len() as f64).ceil() as usize;
+
+        if let Some(v) = sorted.get(rank.saturating_sub(1)) {
+            *self = T::from_index(*v).unwrap_or(*self);
+        }
+    }
+}
+
+impl<T: Discrete> Rate for T {
+    fn rate(&mut self, _prev: &Self, cur: &Self, _kind: SourceKind, _dt: u64) {
+        *self = *cur;
+    }
+}
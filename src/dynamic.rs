@@ -0,0 +1,918 @@
+use super::data::{Accumulator, Presence, Summary, Tally};
+use super::error::Error;
+use super::prelude::*;
+use super::query::Aggregates;
+use super::rtdb::{self, Iter, TableInfo};
+use super::schema::{Leaf, Primitive, Schema};
+use super::Result;
+use std::io::{Read, Seek, Write};
+use std::sync::Arc;
+
+macro_rules! _internal_value_impl {
+    ($($var:ident($type:ty, $wide:ty)),+) => {
+        /// A primitive value of a `Record`. Enums are stored as the `U32`
+        /// index of their variant.
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        pub enum Value {
+            $($var($type)),+
+        }
+
+        $(
+            impl From<$type> for Value {
+                fn from(v: $type) -> Self {
+                    Value::$var(v)
+                }
+            }
+        )+
+
+        #[derive(Debug, Copy, Clone, PartialEq)]
+        enum Summaries {
+            $($var(Summary<$type, $wide>)),+
+        }
+
+        impl Value {
+            pub fn kind(&self) -> Primitive {
+                match self {
+                    $(Value::$var(_) => Primitive::$var),+
+                }
+            }
+
+            pub fn zero(kind: Primitive) -> Self {
+                match kind {
+                    $(Primitive::$var => Value::$var(Default::default())),+
+                }
+            }
+
+            fn hash(kind: Primitive) -> u64 {
+                match kind {
+                    $(Primitive::$var => <$type>::default().get_hash()),+
+                }
+            }
+
+            fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+                match self {
+                    $(Value::$var(v) => v.write_out(writer)),+
+                }
+            }
+
+            fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+                match self {
+                    $(Value::$var(v) => v.read_in(reader)),+
+                }
+            }
+
+            fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64) {
+                match (self, v0, v1) {
+                    $((Value::$var(v), Value::$var(a), Value::$var(b)) => {
+                        v.lerp(a, b, numer, denom)
+                    })+
+                    _ => {}
+                }
+            }
+
+            fn new_acc(&self) -> Summaries {
+                match self {
+                    $(Value::$var(_) => Summaries::$var(<$type>::new_acc())),+
+                }
+            }
+
+            fn accumulate(&self, acc: &mut Summaries, weight: u64) {
+                match (self, acc) {
+                    $((Value::$var(v), Summaries::$var(a)) => v.accumulate(a, weight),)+
+                    _ => {}
+                }
+            }
+
+            fn finish(&mut self, acc: &Summaries, cf: Consolidation, weight: u64) {
+                match (self, acc) {
+                    $((Value::$var(v), Summaries::$var(a)) => v.finish(a, cf, weight),)+
+                    _ => {}
+                }
+            }
+
+            fn quantile(&mut self, column: &[Value], q: f64) {
+                match self {
+                    $(Value::$var(v) => {
+                        let values: Vec<$type> = column
+                            .iter()
+                            .filter_map(|c| match c {
+                                Value::$var(c) => Some(*c),
+                                _ => None,
+                            })
+                            .collect();
+                        v.quantile(&values, q);
+                    })+
+                }
+            }
+
+            fn rate(&mut self, prev: &Self, cur: &Self, kind: SourceKind, dt: u64) {
+                match (self, prev, cur) {
+                    $((Value::$var(v), Value::$var(p), Value::$var(c)) => {
+                        v.rate(p, c, kind, dt)
+                    })+
+                    _ => {}
+                }
+            }
+        }
+
+        impl Accumulator for Summaries {
+            fn merge(&mut self, other: &Self) {
+                match (self, other) {
+                    $((Summaries::$var(a), Summaries::$var(b)) => a.merge(b),)+
+                    _ => {}
+                }
+            }
+
+            fn get_size(&self) -> u64 {
+                match self {
+                    $(Summaries::$var(a) => a.get_size()),+
+                }
+            }
+
+            fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+                match self {
+                    $(Summaries::$var(a) => a.write_out(writer)),+
+                }
+            }
+
+            fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+                match self {
+                    $(Summaries::$var(a) => a.read_in(reader)),+
+                }
+            }
+        }
+    };
+}
+
+_internal_value_impl!(
+    I8(i8, i128),
+    U8(u8, u128),
+    I16(i16, i128),
+    U16(u16, u128),
+    I32(i32, i128),
+    U32(u32, u128),
+    I64(i64, i128),
+    U64(u64, u128),
+    I128(i128, i128),
+    U128(u128, u128),
+    F32(f32, f64),
+    F64(f64, f64),
+    Bool(bool, u64),
+    Char(char, u64)
+);
+
+/// How the values of a record are consolidated, in storage order. An
+/// `Option` takes its presence flag and the values of its inner data point.
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Prim(Primitive),
+    Enum(u32),
+    Option(Vec<Node>),
+}
+
+impl Node {
+    fn parse(schema: &Schema, nodes: &mut Vec<Node>) {
+        match schema {
+            Schema::Primitive(p) => nodes.push(Node::Prim(*p)),
+            Schema::Enum(_, variants) => nodes.push(Node::Enum(variants.len() as u32)),
+            Schema::Array(elem, len) => {
+                for _ in 0..*len {
+                    Node::parse(elem, nodes);
+                }
+            }
+            Schema::Struct(_, fields) => {
+                for (_, field) in fields.iter() {
+                    Node::parse(field, nodes);
+                }
+            }
+            Schema::Option(inner) => {
+                let mut inner_nodes = vec![];
+                Node::parse(inner, &mut inner_nodes);
+                nodes.push(Node::Option(inner_nodes));
+            }
+        }
+    }
+
+    /// Number of values taken by the node.
+    fn width(&self) -> usize {
+        match self {
+            Node::Prim(_) | Node::Enum(_) => 1,
+            Node::Option(inner) => 1 + inner.iter().map(|n| n.width()).sum::<usize>(),
+        }
+    }
+
+    fn new_acc(&self) -> NodeAcc {
+        match self {
+            Node::Prim(p) => NodeAcc::Prim(Value::zero(*p).new_acc()),
+            Node::Enum(count) => NodeAcc::Enum(Tally {
+                weights: vec![0; *count as usize],
+                min: u32::MAX,
+                max: 0,
+                last: 0,
+            }),
+            Node::Option(inner) => NodeAcc::Option(Presence {
+                weight: 0,
+                last: false,
+                acc: RecordAcc(inner.iter().map(|n| n.new_acc()).collect()),
+            }),
+        }
+    }
+}
+
+/// Splits `values` into the values of each of `nodes`.
+fn split<'a, V>(nodes: &'a [Node], mut values: &'a mut [V]) -> Vec<(&'a Node, &'a mut [V])> {
+    let mut res = vec![];
+
+    for node in nodes.iter() {
+        let (head, tail) = values.split_at_mut(node.width().min(values.len()));
+        res.push((node, head));
+        values = tail;
+    }
+
+    res
+}
+
+fn offsets(nodes: &[Node]) -> Vec<(&Node, usize)> {
+    let mut offset = 0;
+    nodes
+        .iter()
+        .map(|n| {
+            let res = (n, offset);
+            offset += n.width();
+            res
+        })
+        .collect()
+}
+
+fn is_some(values: &[Value]) -> bool {
+    matches!(values.first(), Some(Value::Bool(true)))
+}
+
+fn zero_all(values: &mut [Value]) {
+    for v in values.iter_mut() {
+        *v = Value::zero(v.kind());
+    }
+}
+
+fn lerp_nodes(nodes: &[Node], out: &mut [Value], v0: &[Value], v1: &[Value], n: u64, d: u64) {
+    for ((node, out), (_, i)) in split(nodes, out).into_iter().zip(offsets(nodes)) {
+        let a = &v0[i..i + out.len()];
+        let b = &v1[i..i + out.len()];
+
+        match node {
+            Node::Prim(_) => out[0].lerp(&a[0], &b[0], n, d),
+            Node::Option(inner) if is_some(a) && is_some(b) => {
+                out[0] = Value::Bool(true);
+                lerp_nodes(inner, &mut out[1..], &a[1..], &b[1..], n, d);
+            }
+            Node::Enum(_) | Node::Option(_) => {
+                let near = if n.saturating_mul(2) < d { a } else { b };
+                out.copy_from_slice(near);
+            }
+        }
+    }
+}
+
+fn accumulate_nodes(nodes: &[Node], values: &[Value], acc: &mut [NodeAcc], weight: u64) {
+    for ((node, i), acc) in offsets(nodes).into_iter().zip(acc.iter_mut()) {
+        match (node, acc) {
+            (Node::Prim(_), NodeAcc::Prim(a)) => values[i].accumulate(a, weight),
+            (Node::Enum(_), NodeAcc::Enum(a)) => {
+                if let Value::U32(index) = values[i] {
+                    a.add(index, weight);
+                }
+            }
+            (Node::Option(inner), NodeAcc::Option(a)) => {
+                a.last = is_some(&values[i..]);
+
+                if a.last {
+                    a.weight = a.weight.saturating_add(weight);
+                    accumulate_nodes(inner, &values[i + 1..], &mut a.acc.0, weight);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn finish_nodes(nodes: &[Node], out: &mut [Value], acc: &[NodeAcc], cf: Consolidation, w: u64) {
+    for ((node, out), acc) in split(nodes, out).into_iter().zip(acc.iter()) {
+        match (node, acc) {
+            (Node::Prim(_), NodeAcc::Prim(a)) => out[0].finish(a, cf, w),
+            (Node::Enum(count), NodeAcc::Enum(a)) => {
+                let index = a.pick(cf);
+
+                if index < *count {
+                    out[0] = Value::U32(index);
+                }
+            }
+            (Node::Option(inner), NodeAcc::Option(a)) => {
+                let present = match cf {
+                    Consolidation::Last => a.last,
+                    _ => a.weight > 0,
+                };
+
+                if present {
+                    out[0] = Value::Bool(true);
+                    finish_nodes(inner, &mut out[1..], &a.acc.0, cf, a.weight);
+                } else {
+                    zero_all(out);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn quantile_nodes(nodes: &[Node], out: &mut [Value], rows: &[&[Value]], q: f64) {
+    for ((node, out), (_, i)) in split(nodes, out).into_iter().zip(offsets(nodes)) {
+        let column: Vec<&[Value]> = rows.iter().map(|r| &r[i..i + out.len()]).collect();
+
+        match node {
+            Node::Prim(_) | Node::Enum(_) => {
+                let column: Vec<Value> = column.iter().map(|v| v[0]).collect();
+                out[0].quantile(&column, q);
+            }
+            Node::Option(inner) => {
+                let present: Vec<&[Value]> = column
+                    .into_iter()
+                    .filter(|v| is_some(v))
+                    .map(|v| &v[1..])
+                    .collect();
+
+                if present.is_empty() {
+                    zero_all(out);
+                } else {
+                    out[0] = Value::Bool(true);
+                    quantile_nodes(inner, &mut out[1..], &present, q);
+                }
+            }
+        }
+    }
+}
+
+fn rate_nodes(
+    nodes: &[Node],
+    out: &mut [Value],
+    prev: &[Value],
+    cur: &[Value],
+    kind: SourceKind,
+    dt: u64,
+) {
+    for ((node, out), (_, i)) in split(nodes, out).into_iter().zip(offsets(nodes)) {
+        let p = &prev[i..i + out.len()];
+        let c = &cur[i..i + out.len()];
+
+        match node {
+            Node::Prim(_) => out[0].rate(&p[0], &c[0], kind, dt),
+            Node::Option(_) if kind == SourceKind::Gauge => out.copy_from_slice(c),
+            Node::Option(inner) if is_some(p) && is_some(c) => {
+                out[0] = Value::Bool(true);
+                rate_nodes(inner, &mut out[1..], &p[1..], &c[1..], kind, dt);
+            }
+            Node::Option(_) => zero_all(out),
+            Node::Enum(_) => out[0] = c[0],
+        }
+    }
+}
+
+fn write_nodes<W: Write + Seek>(
+    nodes: &[Node],
+    values: &[Value],
+    writer: &mut W,
+) -> std::io::Result<()> {
+    for (node, i) in offsets(nodes) {
+        match node {
+            Node::Prim(_) | Node::Enum(_) => values[i].write_out(writer)?,
+            Node::Option(inner) if is_some(&values[i..]) => {
+                values[i].write_out(writer)?;
+                write_nodes(inner, &values[i + 1..], writer)?;
+            }
+            Node::Option(_) => {
+                for v in values[i..i + node.width()].iter() {
+                    Value::zero(v.kind()).write_out(writer)?;
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn read_nodes<R: Read + Seek>(
+    nodes: &[Node],
+    out: &mut [Value],
+    reader: &mut R,
+) -> std::io::Result<()> {
+    for (node, out) in split(nodes, out) {
+        for v in out.iter_mut() {
+            v.read_in(reader)?;
+        }
+
+        match node {
+            Node::Enum(count) if !matches!(out[0], Value::U32(i) if i < *count) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidData,
+                    "invalid enum index",
+                ));
+            }
+            Node::Option(_) if !is_some(out) => zero_all(out),
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn hash_of(schema: &Schema) -> u64 {
+    match schema {
+        Schema::Primitive(p) => Value::hash(*p),
+        Schema::Array(elem, len) => (0..*len).fold(1_u64, |h, _| h.wrapping_mul(hash_of(elem))),
+        Schema::Struct(_, fields) => fields
+            .iter()
+            .fold(1_u64, |h, (_, f)| h.wrapping_mul(hash_of(f))),
+        Schema::Enum(..) => schema.fingerprint(),
+        Schema::Option(inner) => Value::hash(Primitive::Bool).wrapping_mul(hash_of(inner)),
+    }
+}
+
+#[derive(Debug, PartialEq)]
+struct Layout {
+    schema: Schema,
+    leaves: Vec<Leaf>,
+    nodes: Vec<Node>,
+    fingerprint: u64,
+    hash: u64,
+}
+
+impl Layout {
+    fn new(schema: &Schema) -> Self {
+        let mut nodes = vec![];
+        Node::parse(schema, &mut nodes);
+
+        Self {
+            schema: schema.clone(),
+            leaves: schema.leaves(),
+            nodes,
+            fingerprint: schema.fingerprint(),
+            hash: hash_of(schema),
+        }
+    }
+
+    /// Describes why `value` cannot be stored in the leaf `i`.
+    fn check(&self, i: usize, value: &Value) -> Option<String> {
+        let leaf = &self.leaves[i];
+
+        if value.kind() != leaf.kind {
+            return Some(format!(
+                "`{}` is {} but the value is {}",
+                leaf.path,
+                leaf.kind,
+                value.kind()
+            ));
+        }
+
+        let count = self.enum_count(i)?;
+
+        match value {
+            Value::U32(index) if *index >= count => {
+                Some(format!("`{}` has no variant {}", leaf.path, index))
+            }
+            _ => None,
+        }
+    }
+
+    fn enum_count(&self, leaf: usize) -> Option<u32> {
+        fn find(nodes: &[Node], leaf: usize) -> Option<u32> {
+            for (node, i) in offsets(nodes) {
+                if leaf < i || leaf >= i + node.width() {
+                    continue;
+                }
+
+                return match node {
+                    Node::Enum(count) => Some(*count),
+                    Node::Option(inner) if leaf > i => find(inner, leaf - i - 1),
+                    _ => None,
+                };
+            }
+
+            None
+        }
+
+        find(&self.nodes, leaf)
+    }
+}
+
+/// A data point whose layout is chosen at runtime from a `Schema`. Its
+/// primitive values are kept in the order of `Schema::leaves` and stored
+/// exactly like a typed data point with the same schema.
+///
+/// `Record::default()` has no values and only serves as a placeholder; use
+/// `Record::new` or `DynTable::record` to get a record with a layout.
+#[derive(Debug, Clone, Default)]
+pub struct Record {
+    layout: Option<Arc<Layout>>,
+    values: Vec<Value>,
+}
+
+impl PartialEq for Record {
+    fn eq(&self, other: &Self) -> bool {
+        self.values == other.values && self.get_fingerprint() == other.get_fingerprint()
+    }
+}
+
+impl Record {
+    /// Returns a record with the layout of `schema` and every value zero.
+    pub fn new(schema: &Schema) -> Self {
+        Self::with_layout(Arc::new(Layout::new(schema)))
+    }
+
+    fn with_layout(layout: Arc<Layout>) -> Self {
+        let values = layout.leaves.iter().map(|l| Value::zero(l.kind)).collect();
+
+        Self {
+            layout: Some(layout),
+            values,
+        }
+    }
+
+    /// Returns a record with the layout of `schema` and the given values in
+    /// the order of `Schema::leaves`.
+    pub fn from_values(schema: &Schema, values: Vec<Value>) -> Result<Self> {
+        let mut record = Self::new(schema);
+
+        if values.len() != record.values.len() {
+            return Err(Error::SchemaMismatch(format!(
+                "schema has {} values but {} were given",
+                record.values.len(),
+                values.len()
+            )));
+        }
+
+        for (i, v) in values.into_iter().enumerate() {
+            record.set_index(i, v)?;
+        }
+
+        Ok(record)
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    /// Lists the paths, types and offsets of the values, see
+    /// `Schema::leaves`.
+    pub fn leaves(&self) -> &[Leaf] {
+        self.layout.as_ref().map_or(&[], |l| &l.leaves)
+    }
+
+    /// Returns the value at `path`, such as `link.rx` or `load[2]`.
+    pub fn get(&self, path: &str) -> Option<Value> {
+        let i = self.leaves().iter().position(|l| l.path == path)?;
+        Some(self.values[i])
+    }
+
+    /// Sets the value at `path`, which must have the type of the schema.
+    /// Enums take the `U32` index of a variant and options are set through
+    /// their `some` and `value` fields.
+    pub fn set(&mut self, path: &str, value: Value) -> Result<()> {
+        match self.leaves().iter().position(|l| l.path == path) {
+            Some(i) => self.set_index(i, value),
+            None => Err(Error::SchemaMismatch(format!("`{}` is not stored", path))),
+        }
+    }
+
+    fn set_index(&mut self, i: usize, value: Value) -> Result<()> {
+        if let Some(diff) = self.layout.as_ref().and_then(|l| l.check(i, &value)) {
+            return Err(Error::SchemaMismatch(diff));
+        }
+
+        self.values[i] = value;
+        Ok(())
+    }
+
+    fn nodes(&self) -> &[Node] {
+        self.layout.as_ref().map_or(&[], |l| &l.nodes)
+    }
+
+    fn same_layout(&self, other: &Record) -> bool {
+        match (&self.layout, &other.layout) {
+            (Some(a), Some(b)) => Arc::ptr_eq(a, b) || a.fingerprint == b.fingerprint,
+            (None, None) => true,
+            _ => false,
+        }
+    }
+}
+
+impl DataPoint for Record {
+    fn get_size(&self) -> u64 {
+        self.values.iter().map(|v| v.kind().get_size()).sum()
+    }
+
+    fn get_hash(&self) -> u64 {
+        self.layout.as_ref().map_or(1, |l| l.hash)
+    }
+
+    fn get_schema(&self) -> Schema {
+        match &self.layout {
+            Some(l) => l.schema.clone(),
+            None => Schema::Struct(String::new(), vec![]),
+        }
+    }
+
+    fn get_fingerprint(&self) -> u64 {
+        match &self.layout {
+            Some(l) => l.fingerprint,
+            None => self.get_schema().fingerprint(),
+        }
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        write_nodes(self.nodes(), &self.values, writer)
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        let layout = self.layout.clone();
+        let nodes = layout.as_ref().map_or(&[][..], |l| &l.nodes);
+        read_nodes(nodes, &mut self.values, reader)
+    }
+
+    fn lerp(&mut self, v0: &Self, v1: &Self, numer: u64, denom: u64) {
+        if !(self.same_layout(v0) && self.same_layout(v1)) {
+            return;
+        }
+
+        let layout = self.layout.clone();
+        let nodes = layout.as_ref().map_or(&[][..], |l| &l.nodes);
+        lerp_nodes(
+            nodes,
+            &mut self.values,
+            &v0.values,
+            &v1.values,
+            numer,
+            denom,
+        );
+    }
+
+    fn zeroed(&self) -> Self {
+        let mut zero = self.clone();
+        zero_all(&mut zero.values);
+        zero
+    }
+}
+
+/// Accumulator of a `Record`, laid out like the accumulator of a typed data
+/// point with the same schema.
+#[derive(Debug, Clone, Default)]
+pub struct RecordAcc(Vec<NodeAcc>);
+
+#[derive(Debug, Clone)]
+enum NodeAcc {
+    Prim(Summaries),
+    Enum(Tally<Vec<u64>>),
+    Option(Presence<RecordAcc>),
+}
+
+impl Accumulator for RecordAcc {
+    fn merge(&mut self, other: &Self) {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            match (a, b) {
+                (NodeAcc::Prim(a), NodeAcc::Prim(b)) => a.merge(b),
+                (NodeAcc::Enum(a), NodeAcc::Enum(b)) => a.merge(b),
+                (NodeAcc::Option(a), NodeAcc::Option(b)) => a.merge(b),
+                _ => {}
+            }
+        }
+    }
+
+    fn get_size(&self) -> u64 {
+        self.0
+            .iter()
+            .map(|a| match a {
+                NodeAcc::Prim(a) => a.get_size(),
+                NodeAcc::Enum(a) => a.get_size(),
+                NodeAcc::Option(a) => a.get_size(),
+            })
+            .sum()
+    }
+
+    fn write_out<W: Write + Seek>(&self, writer: &mut W) -> std::io::Result<()> {
+        for a in self.0.iter() {
+            match a {
+                NodeAcc::Prim(a) => a.write_out(writer)?,
+                NodeAcc::Enum(a) => a.write_out(writer)?,
+                NodeAcc::Option(a) => a.write_out(writer)?,
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read_in<R: Read + Seek>(&mut self, reader: &mut R) -> std::io::Result<()> {
+        for a in self.0.iter_mut() {
+            match a {
+                NodeAcc::Prim(a) => a.read_in(reader)?,
+                NodeAcc::Enum(a) => a.read_in(reader)?,
+                NodeAcc::Option(a) => a.read_in(reader)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Consolidates every value like the typed data point with the same schema.
+impl Aggregate for Record {
+    type Acc = RecordAcc;
+
+    fn new_acc() -> Self::Acc {
+        RecordAcc::default()
+    }
+
+    fn accumulate(&self, acc: &mut Self::Acc, weight: u64) {
+        accumulate_nodes(self.nodes(), &self.values, &mut acc.0, weight);
+    }
+
+    fn finish(&mut self, acc: &Self::Acc, cf: Consolidation, weight: u64) {
+        let layout = self.layout.clone();
+        let nodes = layout.as_ref().map_or(&[][..], |l| &l.nodes);
+        finish_nodes(nodes, &mut self.values, &acc.0, cf, weight);
+    }
+
+    fn quantile(&mut self, values: &[Self], q: f64) {
+        let rows: Vec<&[Value]> = values
+            .iter()
+            .filter(|v| self.same_layout(v))
+            .map(|v| &v.values[..])
+            .collect();
+        let layout = self.layout.clone();
+        let nodes = layout.as_ref().map_or(&[][..], |l| &l.nodes);
+        quantile_nodes(nodes, &mut self.values, &rows, q);
+    }
+
+    fn new_acc_like(&self) -> Self::Acc {
+        RecordAcc(self.nodes().iter().map(|n| n.new_acc()).collect())
+    }
+}
+
+/// Applies the source kind of the table to every value, since a schema
+/// does not record per-field source kinds.
+impl Rate for Record {
+    fn rate(&mut self, prev: &Self, cur: &Self, kind: SourceKind, dt: u64) {
+        if !(self.same_layout(prev) && self.same_layout(cur)) {
+            return;
+        }
+
+        let layout = self.layout.clone();
+        let nodes = layout.as_ref().map_or(&[][..], |l| &l.nodes);
+        rate_nodes(nodes, &mut self.values, &prev.values, &cur.values, kind, dt);
+    }
+}
+
+/// A table whose data points are `Record`s with a layout chosen at runtime,
+/// for tools that open tables without knowing their Rust type. Files are
+/// interchangeable with typed tables of the same schema.
+pub struct DynTable<U>
+where
+    U: Read + Write + Seek + Sized,
+{
+    table: Table<Record, U>,
+    layout: Arc<Layout>,
+    zero: Record,
+}
+
+impl<U> DynTable<U>
+where
+    U: Read + Write + Seek + Sized,
+{
+    /// Creates a table with the layout of `first`, which is also its first
+    /// data point as in `Table::new`. Fails with `Error::MissingSchema` if
+    /// `first` has no layout, like `Record::default()`.
+    pub fn new(opts: &Options, first: &Record, data: U) -> Result<Self> {
+        let layout = first.layout.clone().ok_or(Error::MissingSchema)?;
+        let table = Table::new(opts, first, data)?;
+        let zero = first.zeroed();
+        Ok(Self {
+            table,
+            layout,
+            zero,
+        })
+    }
+
+    /// Opens a table with the layout of its stored schema. Fails with
+    /// `Error::MissingSchema` for tables created without one and with
+    /// `Error::InvalidSchema` if the stored one cannot be read.
+    pub fn load(opts: &Options, mut data: U) -> Result<Self> {
+        let schema = rtdb::stored_schema(&mut data)?.ok_or(Error::MissingSchema)?;
+        let layout = Arc::new(Layout::new(&schema));
+        let zero = Record::with_layout(layout.clone());
+        let table = Table::load(opts, &zero, data)?;
+        Ok(Self {
+            table,
+            layout,
+            zero,
+        })
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.layout.schema
+    }
+
+    /// Returns a zero record in the layout of the table, to be filled with
+    /// `Record::set`.
+    pub fn record(&self) -> Record {
+        self.zero.clone()
+    }
+
+    fn check(&self, dp: &Record) -> Result<()> {
+        if dp.same_layout(&self.zero) {
+            return Ok(());
+        }
+
+        let diff = self.schema().diff(&dp.get_schema());
+        Err(Error::SchemaMismatch(diff.unwrap_or_else(|| {
+            "data point has a different layout".to_string()
+        })))
+    }
+
+    pub fn insert(&mut self, t_now: u64, dp: &Record) -> Result<()> {
+        self.check(dp)?;
+        self.table.insert(t_now, dp)
+    }
+
+    pub fn insert_many<I: IntoIterator<Item = (u64, Record)>>(&mut self, items: I) -> Result<()> {
+        let items: Vec<(u64, Record)> = items.into_iter().collect();
+
+        for (i, (_, dp)) in items.iter().enumerate() {
+            self.check(dp)
+                .map_err(|e| Error::InsertFailed(i, Box::new(e)))?;
+        }
+
+        self.table.insert_many(items)
+    }
+
+    pub fn update_at(&mut self, t: u64, dp: &Record) -> Result<()> {
+        self.check(dp)?;
+        self.table.update_at(t, dp)
+    }
+
+    pub fn update_range(&mut self, start: u64, dps: &[Record]) -> Result<()> {
+        for dp in dps.iter() {
+            self.check(dp)?;
+        }
+
+        self.table.update_range(start, dps)
+    }
+
+    pub fn get(&mut self, t: u64) -> Result<Option<&Record>> {
+        self.table.get(t)
+    }
+
+    pub fn first(&mut self) -> Result<(u64, Option<&Record>)> {
+        self.table.first()
+    }
+
+    pub fn last(&mut self) -> Result<(u64, Option<&Record>)> {
+        self.table.last()
+    }
+
+    pub fn iter(&mut self) -> Result<Iter<'_, Record, U>> {
+        self.table.iter()
+    }
+
+    pub fn range(&mut self, start: u64, end: u64) -> Result<Iter<'_, Record, U>> {
+        self.table.range(start, end)
+    }
+
+    pub fn aggregate(&mut self, start: u64, end: u64) -> Result<Aggregates<Record>> {
+        self.table.aggregate(start, end)
+    }
+
+    pub fn quantile(&mut self, start: u64, end: u64, q: f64) -> Result<Record> {
+        self.table.quantile(start, end, q)
+    }
+
+    pub fn verify(&mut self) -> Result<()> {
+        self.table.verify()
+    }
+
+    pub fn version(&self) -> u32 {
+        self.table.version()
+    }
+
+    pub fn info(&self) -> TableInfo {
+        self.table.info()
+    }
+
+    pub fn tune(&mut self, opts: &Options) -> Result<()> {
+        self.table.tune(opts)
+    }
+
+    pub fn set_sync(&mut self, sync: fn(&mut U) -> std::io::Result<()>) {
+        self.table.set_sync(sync);
+    }
+
+    pub fn into_inner(self) -> U {
+        self.table.into_inner()
+    }
+}
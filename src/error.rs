@@ -4,17 +4,35 @@ use std::fmt;
 pub enum Error {
     IntConvError,
     InvalidMagicNumber,
+    UnsupportedVersion(u32),
+    UnsupportedFlags(u64),
+    InvalidHeader,
     InvalidDpSize,
     InvalidDpHash,
+    InvalidSchema,
+    SchemaMismatch(String),
+    MissingSchema,
+    OptionsMismatch(String),
     InvalidDpCount,
     InvalidTimeStep,
     InvalidStreamLen,
     UpdateTooEarly,
     UpdateTooLate,
     InvalidSkip,
+    InvalidSkipMode,
+    InvalidHeartbeat,
+    InvalidGapPolicy,
     MaxSkipExceeded,
     OutOfRangePast,
     OutOfRangeFuture,
+    StaleSlot,
+    InvalidArchive,
+    InvalidPending,
+    InvalidSource,
+    InvalidJournal,
+    PendingRedo,
+    ChecksumMismatch(u64),
+    InsertFailed(usize, Box<Error>),
     IoError(std::io::Error),
 }
 
@@ -26,17 +44,41 @@ impl fmt::Display for Error {
         match self {
             IntConvError => write!(f, "integer conversion overflowed"),
             InvalidMagicNumber => write!(f, "invalid magic number"),
+            UnsupportedVersion(v) => write!(f, "unsupported format version {}", v),
+            UnsupportedFlags(flags) => write!(f, "unsupported format flags {:#x}", flags),
+            InvalidHeader => write!(f, "invalid table header"),
             InvalidDpSize => write!(f, "dp size must be non-zero"),
             InvalidDpHash => write!(f, "invalid datapoint hash value"),
+            InvalidSchema => write!(f, "invalid data point schema"),
+            SchemaMismatch(diff) => write!(f, "data point does not match schema: {}", diff),
+            MissingSchema => write!(f, "table has no stored schema"),
+            OptionsMismatch(diff) => write!(f, "options do not match the table: {}", diff),
             InvalidDpCount => write!(f, "dp count must be at least 2"),
             InvalidTimeStep => write!(f, "time step must be non-zero"),
             InvalidStreamLen => write!(f, "i/o stream has wrong length"),
             UpdateTooEarly => write!(f, "update time is too early"),
             UpdateTooLate => write!(f, "update time is too late"),
             InvalidSkip => write!(f, "max fwd skip cannot be greater than dp count - 2"),
+            InvalidSkipMode => write!(
+                f,
+                "fwd skip mode requires a table that tracks unknown values"
+            ),
+            InvalidHeartbeat => write!(f, "heartbeat cannot be shorter than time step"),
+            InvalidGapPolicy => write!(f, "invalid long gap policy"),
             MaxSkipExceeded => write!(f, "max fwd skip value exceeded"),
             OutOfRangePast => write!(f, "requested time is too far in the past"),
             OutOfRangeFuture => write!(f, "requested time is in the future"),
+            StaleSlot => write!(f, "slot holds data from a previous cycle"),
+            InvalidArchive => write!(f, "invalid archive definition"),
+            InvalidPending => write!(f, "invalid pending data point"),
+            InvalidSource => write!(f, "invalid data source"),
+            InvalidJournal => write!(f, "no intact journal state found"),
+            PendingRedo => write!(
+                f,
+                "journal holds a change that must be replayed on a writable open"
+            ),
+            ChecksumMismatch(offset) => write!(f, "checksum mismatch at offset {}", offset),
+            InsertFailed(i, e) => write!(f, "insert of element {} failed: {}", i, e),
             IoError(e) => e.fmt(f),
         }
     }
@@ -45,20 +87,58 @@ impl fmt::Display for Error {
 impl PartialEq for Error {
     fn eq(&self, other: &Self) -> bool {
         use Error::*;
+
+        if let (InsertFailed(i, e), InsertFailed(j, f)) = (self, other) {
+            return i == j && e == f;
+        }
+
+        if let (ChecksumMismatch(a), ChecksumMismatch(b)) = (self, other) {
+            return a == b;
+        }
+
+        if let (SchemaMismatch(a), SchemaMismatch(b)) = (self, other) {
+            return a == b;
+        }
+
+        if let (OptionsMismatch(a), OptionsMismatch(b)) = (self, other) {
+            return a == b;
+        }
+
+        if let (UnsupportedVersion(a), UnsupportedVersion(b)) = (self, other) {
+            return a == b;
+        }
+
+        if let (UnsupportedFlags(a), UnsupportedFlags(b)) = (self, other) {
+            return a == b;
+        }
+
         matches!(
             (self, other),
             (IntConvError, IntConvError)
                 | (InvalidMagicNumber, InvalidMagicNumber)
+                | (InvalidHeader, InvalidHeader)
                 | (InvalidDpSize, InvalidDpSize)
                 | (InvalidDpHash, InvalidDpHash)
+                | (InvalidSchema, InvalidSchema)
+                | (MissingSchema, MissingSchema)
                 | (InvalidDpCount, InvalidDpCount)
                 | (InvalidTimeStep, InvalidTimeStep)
                 | (InvalidStreamLen, InvalidStreamLen)
                 | (UpdateTooEarly, UpdateTooEarly)
                 | (UpdateTooLate, UpdateTooLate)
+                | (InvalidSkip, InvalidSkip)
+                | (InvalidSkipMode, InvalidSkipMode)
+                | (InvalidHeartbeat, InvalidHeartbeat)
+                | (InvalidGapPolicy, InvalidGapPolicy)
                 | (MaxSkipExceeded, MaxSkipExceeded)
                 | (OutOfRangePast, OutOfRangePast)
                 | (OutOfRangeFuture, OutOfRangeFuture)
+                | (StaleSlot, StaleSlot)
+                | (InvalidArchive, InvalidArchive)
+                | (InvalidPending, InvalidPending)
+                | (InvalidSource, InvalidSource)
+                | (InvalidJournal, InvalidJournal)
+                | (PendingRedo, PendingRedo)
                 | (IoError(_), IoError(_))
         )
     }
@@ -1,16 +1,25 @@
 pub mod create;
 pub mod data;
+pub mod dynamic;
 pub mod error;
 pub mod load;
 pub mod options;
+pub mod query;
+pub mod reader;
 pub mod rtdb;
+pub mod schema;
+
+#[cfg(feature = "derive")]
+pub use roundtable_derive::DataPoint;
 
 pub type Error = self::error::Error;
 pub type Result<T> = std::result::Result<T, Error>;
 
 pub mod prelude {
-    pub use super::data::DataPoint;
-    pub use super::options::{FwdSkipMode, Options};
+    pub use super::data::{Aggregate, DataPoint, Rate};
+    pub use super::options::{Consolidation, FwdSkipMode, LongGapPolicy, Options, SourceKind};
     pub use super::rtdb::Table;
+    #[cfg(feature = "derive")]
+    pub use roundtable_derive::DataPoint;
     pub type InMemoryTable<T> = Table<T, std::io::Cursor<Vec<u8>>>;
 }
@@ -1,12 +1,14 @@
 use super::error::Error;
 use super::prelude::*;
+use super::reader::TableReader;
+use super::rtdb::{TableInfo, VERSION};
 use super::Result;
 use std::fs::{File, OpenOptions};
 use std::io::prelude::*;
 use std::io::Cursor;
 use std::path::Path;
 
-pub fn from_buffer<T: DataPoint + Copy + Default, U: AsRef<[u8]>>(
+pub fn from_buffer<T: DataPoint + Aggregate + Rate + Clone + Default, U: AsRef<[u8]>>(
     opts: Options,
     buf: U,
 ) -> Result<Table<T, Cursor<U>>>
@@ -18,7 +20,7 @@ where
     Table::load(&opts, &dp, data)
 }
 
-pub fn from_file<T: DataPoint + Copy + Default, P: AsRef<Path>>(
+pub fn from_file<T: DataPoint + Aggregate + Rate + Clone + Default, P: AsRef<Path>>(
     opts: Options,
     path: P,
 ) -> Result<Table<T, File>> {
@@ -28,5 +30,53 @@ pub fn from_file<T: DataPoint + Copy + Default, P: AsRef<Path>>(
         .write(true)
         .open(path)
         .map_err(Error::IoError)?;
-    Table::load(&opts, &dp, file)
+    let mut table = Table::load(&opts, &dp, file)?;
+    table.set_sync(|f| f.sync_data());
+    Ok(table)
+}
+
+/// Opens the table at `path` for reading only, without requesting write
+/// access to the file.
+pub fn read_file<T: DataPoint + Aggregate + Rate + Clone + Default, P: AsRef<Path>>(
+    opts: Options,
+    path: P,
+) -> Result<TableReader<T, File>> {
+    let dp = T::default();
+    let file = File::open(path).map_err(Error::IoError)?;
+    TableReader::load(&opts, &dp, file)
+}
+
+/// Rewrites the file at `path` in the newest format version if it is older
+/// and opens it. The new file is written next to the old one and renamed
+/// over it once complete.
+pub fn upgrade_file<T: DataPoint + Aggregate + Rate + Clone + Default, P: AsRef<Path>>(
+    opts: Options,
+    path: P,
+) -> Result<Table<T, File>> {
+    let table = from_file::<T, _>(opts, &path)?;
+
+    if table.version() == VERSION {
+        return Ok(table);
+    }
+
+    let tmp = path.as_ref().with_extension("upgrade");
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(&tmp)
+        .map_err(Error::IoError)?;
+    let file = table.upgrade(&opts, file)?.into_inner();
+    file.sync_all().map_err(Error::IoError)?;
+    drop(file);
+    std::fs::rename(&tmp, &path).map_err(Error::IoError)?;
+    from_file(opts, path)
+}
+
+/// Reads the header and schema of the table at `path` without opening it
+/// for writing or knowing its data point type.
+pub fn peek_file<P: AsRef<Path>>(path: P) -> Result<TableInfo> {
+    let mut file = File::open(path).map_err(Error::IoError)?;
+    TableInfo::peek(&mut file)
 }
@@ -1,9 +1,165 @@
-#[derive(Debug, Copy, Clone)]
+pub(crate) const MAX_ARCHIVES: usize = 8;
+pub(crate) const DEFAULT_MAX_FWD_SKIP: u64 = 2;
+pub(crate) const DEFAULT_FWD_SKIP_MODE: FwdSkipMode = FwdSkipMode::Nearest;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum FwdSkipMode {
     DoNothing,
     Linear,
     Nearest,
     Zeroed,
+    Unknown,
+}
+
+impl FwdSkipMode {
+    pub(crate) fn to_u32(self) -> u32 {
+        use FwdSkipMode::*;
+        match self {
+            DoNothing => 0,
+            Linear => 1,
+            Nearest => 2,
+            Zeroed => 3,
+            Unknown => 4,
+        }
+    }
+
+    pub(crate) fn from_u32(val: u32) -> Option<Self> {
+        use FwdSkipMode::*;
+        match val {
+            0 => Some(DoNothing),
+            1 => Some(Linear),
+            2 => Some(Nearest),
+            3 => Some(Zeroed),
+            4 => Some(Unknown),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Consolidation {
+    Average,
+    Min,
+    Max,
+    Last,
+    Sum,
+}
+
+impl Consolidation {
+    pub(crate) fn to_u32(self) -> u32 {
+        use Consolidation::*;
+        match self {
+            Average => 0,
+            Min => 1,
+            Max => 2,
+            Last => 3,
+            Sum => 4,
+        }
+    }
+
+    pub(crate) fn from_u32(val: u32) -> Option<Self> {
+        use Consolidation::*;
+        match val {
+            0 => Some(Average),
+            1 => Some(Min),
+            2 => Some(Max),
+            3 => Some(Last),
+            4 => Some(Sum),
+            _ => None,
+        }
+    }
+}
+
+/// How raw values passed to `Table::insert` are turned into stored values.
+///
+/// `Gauge` stores values as they are. `Counter` stores the per-second rate of
+/// a monotonically increasing counter, assuming a 32-bit and then a 64-bit
+/// wrap when it decreases. `Derive` stores the per-second rate of change,
+/// which may be negative after a reset. `Absolute` stores the value divided
+/// by the time since the previous update, for counters that reset on read.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SourceKind {
+    Gauge,
+    Counter,
+    Derive,
+    Absolute,
+}
+
+impl SourceKind {
+    pub(crate) fn to_u32(self) -> u32 {
+        use SourceKind::*;
+        match self {
+            Gauge => 0,
+            Counter => 1,
+            Derive => 2,
+            Absolute => 3,
+        }
+    }
+
+    pub(crate) fn from_u32(val: u32) -> Option<Self> {
+        use SourceKind::*;
+        match val {
+            0 => Some(Gauge),
+            1 => Some(Counter),
+            2 => Some(Derive),
+            3 => Some(Absolute),
+            _ => None,
+        }
+    }
+}
+
+/// What `Table::insert` does when the time since the previous update is
+/// longer than the whole primary ring.
+///
+/// `Error` refuses the update with `UpdateTooLate`. `Reset` discards the
+/// table contents and restarts it at the new time. `Fill` marks every slot in
+/// the gap as unknown, or `T::default()` for tables that do not track unknown
+/// values, and keeps the archives going.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum LongGapPolicy {
+    Error,
+    Reset,
+    Fill,
+}
+
+impl LongGapPolicy {
+    pub(crate) fn to_u32(self) -> u32 {
+        use LongGapPolicy::*;
+        match self {
+            Error => 0,
+            Reset => 1,
+            Fill => 2,
+        }
+    }
+
+    pub(crate) fn from_u32(val: u32) -> Option<Self> {
+        use LongGapPolicy::*;
+        match val {
+            0 => Some(Error),
+            1 => Some(Reset),
+            2 => Some(Fill),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub(crate) struct ArchiveOptions {
+    pub(crate) cf: Consolidation,
+    pub(crate) xff: f64,
+    pub(crate) t_step: u64,
+    pub(crate) t_total: u64,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        Self {
+            cf: Consolidation::Average,
+            xff: 0.0,
+            t_step: 0,
+            t_total: 0,
+        }
+    }
 }
 
 #[derive(Debug, Copy, Clone)]
@@ -14,8 +170,19 @@ pub struct Options {
     pub(crate) overwrite: bool,
     pub(crate) preallocate: bool,
     pub(crate) ignore_hash: bool,
-    pub(crate) max_fwd_skip: u64,
-    pub(crate) fwd_skip_mode: FwdSkipMode,
+    pub(crate) track_unknown: bool,
+    pub(crate) stamp_slots: bool,
+    pub(crate) pending: Option<Consolidation>,
+    pub(crate) source: SourceKind,
+    pub(crate) heartbeat: u64,
+    pub(crate) gap_policy: LongGapPolicy,
+    pub(crate) journal: bool,
+    pub(crate) checksums: bool,
+    pub(crate) max_fwd_skip: Option<u64>,
+    pub(crate) fwd_skip_mode: Option<FwdSkipMode>,
+    pub(crate) strict: bool,
+    pub(crate) archives: [ArchiveOptions; MAX_ARCHIVES],
+    pub(crate) archive_count: usize,
 }
 
 impl Options {
@@ -27,8 +194,19 @@ impl Options {
             overwrite: false,
             preallocate: false,
             ignore_hash: false,
-            max_fwd_skip: 2,
-            fwd_skip_mode: FwdSkipMode::Nearest,
+            track_unknown: false,
+            stamp_slots: false,
+            pending: None,
+            source: SourceKind::Gauge,
+            heartbeat: 0,
+            gap_policy: LongGapPolicy::Error,
+            journal: false,
+            checksums: false,
+            max_fwd_skip: None,
+            fwd_skip_mode: None,
+            strict: false,
+            archives: [ArchiveOptions::default(); MAX_ARCHIVES],
+            archive_count: 0,
         }
     }
 
@@ -46,16 +224,107 @@ impl Options {
         }
     }
 
+    /// Stores a validity flag with every slot so that gaps can be told apart
+    /// from real values. Implied by `FwdSkipMode::Unknown`.
+    pub fn track_unknown(self, val: bool) -> Self {
+        Self {
+            track_unknown: val,
+            ..self
+        }
+    }
+
+    /// Stores the time of every slot next to its data point so that reads can
+    /// detect slots left over from an earlier cycle of the ring.
+    pub fn stamp_slots(self, val: bool) -> Self {
+        Self {
+            stamp_slots: val,
+            ..self
+        }
+    }
+
+    /// Accepts several updates within one step and combines them with `cf`
+    /// into a pending data point that is committed when the step closes.
+    /// With `Consolidation::Average`, each update is weighted by the time
+    /// elapsed since the previous update within the same step.
+    pub fn pending(self, cf: Consolidation) -> Self {
+        Self {
+            pending: Some(cf),
+            ..self
+        }
+    }
+
+    /// Converts the raw values passed to `Table::insert` according to `kind`.
+    /// The previous raw value is kept in the file, and the first data point
+    /// of a table that is not a gauge is stored as unknown.
+    pub fn source(self, kind: SourceKind) -> Self {
+        Self {
+            source: kind,
+            ..self
+        }
+    }
+
+    /// Sets the largest number of steps an update may skip, 2 by default.
+    /// The value is stored in the table; loading it with a different value
+    /// fails with `Error::OptionsMismatch`, see `Table::tune`.
     pub fn max_fwd_skip(self, val: u64) -> Self {
         Self {
-            max_fwd_skip: val,
+            max_fwd_skip: Some(val),
+            ..self
+        }
+    }
+
+    /// Sets the longest time allowed between two updates. Shorter gaps are
+    /// filled according to the fwd skip mode, longer ones are marked unknown,
+    /// and `max_fwd_skip` is no longer enforced. Implies `track_unknown`.
+    pub fn heartbeat(self, val: u64) -> Self {
+        Self {
+            heartbeat: val,
+            ..self
+        }
+    }
+
+    /// Keeps two alternating copies of the table state and a redo record of
+    /// the last insert or update, so that a table interrupted by a crash
+    /// loads with the last committed change. See `Table::set_sync`.
+    pub fn journal(self, val: bool) -> Self {
+        Self {
+            journal: val,
+            ..self
+        }
+    }
+
+    /// Stores a checksum after the table state and after every slot. Reads
+    /// of a damaged slot fail with `Error::ChecksumMismatch`, and
+    /// `Table::verify` checks the whole table.
+    pub fn checksums(self, val: bool) -> Self {
+        Self {
+            checksums: val,
+            ..self
+        }
+    }
+
+    pub fn long_gap_policy(self, val: LongGapPolicy) -> Self {
+        Self {
+            gap_policy: val,
             ..self
         }
     }
 
+    /// Sets how skipped slots are filled, `FwdSkipMode::Nearest` by default.
+    /// Stored in the table like `max_fwd_skip`.
     pub fn fwd_skip_mode(self, val: FwdSkipMode) -> Self {
         Self {
-            fwd_skip_mode: val,
+            fwd_skip_mode: Some(val),
+            ..self
+        }
+    }
+
+    /// Makes `Table::load` fail with `Error::OptionsMismatch` when any of
+    /// these options differs from the ones the table was created with, as
+    /// checked by `Table::check_options`.
+    pub fn strict(self, val: bool) -> Self {
+        Self {
+            strict: val,
             ..self
         }
     }
@@ -67,7 +336,50 @@ impl Options {
         }
     }
 
+    /// Adds a consolidated archive that keeps `total_time` worth of rows, each
+    /// combining `time_step / t_step` primary data points with `cf`. A row is
+    /// unknown when more than `xff` of its primary data points are unknown or
+    /// were left untouched by `FwdSkipMode::DoNothing`. Without
+    /// `track_unknown`, such rows are written as `T::default()`.
+    pub fn archive(self, cf: Consolidation, xff: f64, time_step: u64, total_time: u64) -> Self {
+        let mut archives = self.archives;
+
+        if let Some(a) = archives.get_mut(self.archive_count) {
+            *a = ArchiveOptions {
+                cf,
+                xff,
+                t_step: time_step,
+                t_total: total_time,
+            };
+        }
+
+        Self {
+            archives,
+            archive_count: self.archive_count + 1,
+            ..self
+        }
+    }
+
     pub(crate) fn dp_count(&self) -> u64 {
         self.t_total.checked_div(self.t_step).unwrap_or(0_u64)
     }
+
+    pub(crate) fn tracks_unknown(&self) -> bool {
+        self.track_unknown
+            || self.heartbeat > 0
+            || matches!(self.fwd_skip_mode, Some(FwdSkipMode::Unknown))
+    }
+
+    /// Returns the fwd skip settings, with the ones that were not set taken
+    /// from `fallback`.
+    pub(crate) fn skip_or(&self, fallback: (u64, FwdSkipMode)) -> (u64, FwdSkipMode) {
+        (
+            self.max_fwd_skip.unwrap_or(fallback.0),
+            self.fwd_skip_mode.unwrap_or(fallback.1),
+        )
+    }
+
+    pub(crate) fn archives(&self) -> &[ArchiveOptions] {
+        &self.archives[..self.archive_count.min(MAX_ARCHIVES)]
+    }
 }
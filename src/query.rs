@@ -0,0 +1,157 @@
+use super::data::Discrete;
+use super::prelude::*;
+use super::Result;
+use std::io::{Read, Seek, Write};
+
+/// Result of a single pass over a time window, see `Table::aggregate`.
+#[derive(Debug, Copy, Clone)]
+pub struct Aggregates<T: Aggregate> {
+    acc: T::Acc,
+    count: u64,
+    zero: T,
+}
+
+impl<T> Aggregates<T>
+where
+    T: Aggregate + Clone,
+{
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> T {
+        self.get(Consolidation::Min)
+    }
+
+    pub fn max(&self) -> T {
+        self.get(Consolidation::Max)
+    }
+
+    pub fn mean(&self) -> T {
+        self.get(Consolidation::Average)
+    }
+
+    pub fn sum(&self) -> T {
+        self.get(Consolidation::Sum)
+    }
+
+    pub fn last(&self) -> T {
+        self.get(Consolidation::Last)
+    }
+
+    /// Returns the window consolidated with `cf`, or a zero data point if
+    /// the window held no data points.
+    pub fn get(&self, cf: Consolidation) -> T {
+        let mut dp = self.zero.clone();
+
+        if self.count > 0 {
+            dp.finish(&self.acc, cf, self.count);
+        }
+
+        dp
+    }
+}
+
+/// Time spent in each state of a `Discrete` data point, see
+/// `Table::time_in_states`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StateTimes<T> {
+    /// Seconds spent in each state, in declaration order.
+    pub states: Vec<(T, u64)>,
+    /// Seconds of unknown slots.
+    pub unknown: u64,
+}
+
+impl<T: Discrete> StateTimes<T> {
+    /// Seconds spent in `state`.
+    pub fn get(&self, state: T) -> u64 {
+        let i = state.to_index() as usize;
+        self.states.get(i).map_or(0, |(_, t)| *t)
+    }
+
+    /// Seconds spent in any known state.
+    pub fn known(&self) -> u64 {
+        self.states.iter().map(|(_, t)| t).sum()
+    }
+
+    /// Share of the known time spent in `state`, between 0 and 1, e.g. the
+    /// availability of a service for `state = Up`. Returns `None` if no
+    /// state is known.
+    pub fn share(&self, state: T) -> Option<f64> {
+        match self.known() {
+            0 => None,
+            known => Some(self.get(state) as f64 / known as f64),
+        }
+    }
+}
+
+impl<T, U> Table<T, U>
+where
+    T: DataPoint + Aggregate + Rate + Clone + Default,
+    U: Read + Write + Seek + Sized,
+{
+    /// Computes count, min, max, mean, sum and last value of every field
+    /// over the data points that `range(start, end)` would return. Unknown
+    /// slots are skipped and not counted. Fails with the error of the first
+    /// slot that cannot be read.
+    pub fn aggregate(&mut self, start: u64, end: u64) -> Result<Aggregates<T>> {
+        let zero = self.zero().clone();
+        let mut acc = zero.new_acc_like();
+        let mut count = 0;
+        let mut iter = self.range(start, end)?;
+
+        for dp in iter.by_ref().filter_map(|(_, dp)| dp) {
+            dp.accumulate(&mut acc, 1);
+            count += 1;
+        }
+
+        match iter.take_error() {
+            Some(e) => Err(e),
+            None => Ok(Aggregates { acc, count, zero }),
+        }
+    }
+
+    /// Computes the `q`-quantile of every field over the data points that
+    /// `range(start, end)` would return, e.g. `q = 0.95` for the 95th
+    /// percentile. Unknown slots are skipped. Fails with the error of the
+    /// first slot that cannot be read.
+    pub fn quantile(&mut self, start: u64, end: u64, q: f64) -> Result<T> {
+        let mut iter = self.range(start, end)?;
+        let values: Vec<T> = iter.by_ref().filter_map(|(_, dp)| dp).collect();
+
+        if let Some(e) = iter.take_error() {
+            return Err(e);
+        }
+
+        let mut dp = self.zero().clone();
+        dp.quantile(&values, q);
+        Ok(dp)
+    }
+
+    /// Adds up the time spent in each state over the slots that
+    /// `range(start, end)` would return, counting every slot as one step of
+    /// the ring it was read from.
+    pub fn time_in_states(&mut self, start: u64, end: u64) -> Result<StateTimes<T>>
+    where
+        T: Discrete,
+    {
+        let mut states: Vec<(T, u64)> = (0..T::VARIANTS.len() as u32)
+            .filter_map(|i| Some((T::from_index(i)?, 0)))
+            .collect();
+        let mut unknown = 0;
+        let mut iter = self.range(start, end)?;
+        let step = iter.step();
+
+        for (_, dp) in iter.by_ref() {
+            match dp.and_then(|v| states.get_mut(v.to_index() as usize)) {
+                Some((_, t)) => *t += step,
+                None => unknown += step,
+            }
+        }
+
+        match iter.take_error() {
+            Some(e) => Err(e),
+            None => Ok(StateTimes { states, unknown }),
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use super::data::Discrete;
+use super::prelude::*;
+use super::query::{Aggregates, StateTimes};
+use super::rtdb::{Iter, TableInfo};
+use super::schema::Schema;
+use super::Result;
+use std::io::{Read, Seek, SeekFrom, Write};
+
+/// Wraps a stream that is only read from. Writes fail with
+/// `ErrorKind::PermissionDenied`.
+pub struct ReadOnly<R>(R);
+
+impl<R> ReadOnly<R> {
+    pub fn into_inner(self) -> R {
+        self.0
+    }
+}
+
+impl<R: Read> Read for ReadOnly<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        self.0.read(buf)
+    }
+}
+
+impl<R: Seek> Seek for ReadOnly<R> {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.0.seek(pos)
+    }
+}
+
+impl<R> Write for ReadOnly<R> {
+    fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::PermissionDenied,
+            "table is read-only",
+        ))
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// A table opened for reading only, from a stream that needs just `Read`
+/// and `Seek`. Loading never writes, so a journaled change that was
+/// interrupted by a crash makes it fail with `Error::PendingRedo` until the
+/// table has been opened once for writing.
+pub struct TableReader<T, R>
+where
+    T: DataPoint + Aggregate + Rate + Clone + Default,
+    R: Read + Seek,
+{
+    table: Table<T, ReadOnly<R>>,
+}
+
+impl<T, R> TableReader<T, R>
+where
+    T: DataPoint + Aggregate + Rate + Clone + Default,
+    R: Read + Seek,
+{
+    /// Opens the table in `data` with the same checks as `Table::load`.
+    pub fn load(opts: &Options, dp: &T, data: R) -> Result<Self> {
+        let table = Table::load_read_only(opts, dp, ReadOnly(data))?;
+        Ok(Self { table })
+    }
+
+    pub fn get(&mut self, t: u64) -> Result<Option<&T>> {
+        self.table.get(t)
+    }
+
+    pub fn first(&mut self) -> Result<(u64, Option<&T>)> {
+        self.table.first()
+    }
+
+    pub fn last(&mut self) -> Result<(u64, Option<&T>)> {
+        self.table.last()
+    }
+
+    pub fn iter(&mut self) -> Result<Iter<'_, T, ReadOnly<R>>> {
+        self.table.iter()
+    }
+
+    pub fn range(&mut self, start: u64, end: u64) -> Result<Iter<'_, T, ReadOnly<R>>> {
+        self.table.range(start, end)
+    }
+
+    pub fn aggregate(&mut self, start: u64, end: u64) -> Result<Aggregates<T>> {
+        self.table.aggregate(start, end)
+    }
+
+    pub fn quantile(&mut self, start: u64, end: u64, q: f64) -> Result<T> {
+        self.table.quantile(start, end, q)
+    }
+
+    pub fn time_in_states(&mut self, start: u64, end: u64) -> Result<StateTimes<T>>
+    where
+        T: Discrete,
+    {
+        self.table.time_in_states(start, end)
+    }
+
+    pub fn verify(&mut self) -> Result<()> {
+        self.table.verify()
+    }
+
+    pub fn schema(&self) -> Option<&Schema> {
+        self.table.schema()
+    }
+
+    pub fn version(&self) -> u32 {
+        self.table.version()
+    }
+
+    pub fn info(&self) -> TableInfo {
+        self.table.info()
+    }
+
+    pub fn into_inner(self) -> R {
+        self.table.into_inner().into_inner()
+    }
+}
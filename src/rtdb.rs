@@ -1,12 +1,53 @@
+use super::data::Accumulator;
+use super::dynamic::Record;
 use super::error::Error;
+use super::options::{
+    ArchiveOptions, Consolidation, LongGapPolicy, SourceKind, DEFAULT_FWD_SKIP_MODE,
+    DEFAULT_MAX_FWD_SKIP, MAX_ARCHIVES,
+};
 use super::prelude::*;
+use super::schema::Schema;
 use super::Result;
-use std::io::SeekFrom;
-
-const RTDB: u32 = 0x42445452;
+use std::io::{Cursor, SeekFrom};
+
+const RTDB: u32 = 0x56445452;
+const RTDB_V1: u32 = 0x42445452;
+/// Newest on-disk format version, written by `Table::new` and
+/// `Table::upgrade`.
+pub const VERSION: u32 = 2;
+const FLAG_UNKNOWN: u64 = 1;
+const FLAG_STAMP: u64 = 2;
+const FLAG_PENDING: u64 = 4;
+const FLAG_SOURCE: u64 = 8;
+const FLAG_JOURNAL: u64 = 16;
+const FLAG_CHECKSUM: u64 = 32;
+const FLAG_SCHEMA: u64 = 64;
+const FLAG_FINGERPRINT: u64 = 128;
+const FLAG_SKIP: u64 = 256;
+const KNOWN_FLAGS: u64 = 511;
+const MAX_SCHEMA_SIZE: u64 = 1 << 20;
+const OP_INSERT: u32 = 0;
+const OP_UPDATE: u32 = 1;
 
 super::datapoint! {
     pub struct Header {
+        magic: u32,
+        version: u32,
+        dp_size: u64,
+        dp_hash: u64,
+        dp_count: u64,
+        t_start: u64,
+        t_step: u64,
+        heartbeat: u64,
+        gap_policy: u32,
+        flags: u64,
+        archive_count: u64,
+        t_updated: u64,
+    }
+
+    // Header of version 1 files, which have no flags and store only the
+    // primary ring.
+    pub struct LegacyHeader {
         magic: u32,
         dp_size: u64,
         dp_hash: u64,
@@ -15,17 +56,72 @@ super::datapoint! {
         t_step: u64,
         t_updated: u64,
     }
+
+    pub struct ArchiveHeader {
+        cf: u32,
+        xff: f64,
+        steps: u64,
+        rows: u64,
+        known: u64,
+        unknown: u64,
+    }
+
+    pub struct PendingHeader {
+        cf: u32,
+        weight: u64,
+    }
+
+    pub struct SourceHeader {
+        kind: u32,
+        t_raw: u64,
+    }
+
+    pub struct SkipHeader {
+        mode: u32,
+        max_skip: u64,
+    }
 }
 
 impl Header {
     pub fn new<T: DataPoint>(opts: &Options, dp: &T) -> Self {
+        let mut flags = FLAG_SCHEMA | FLAG_FINGERPRINT | FLAG_SKIP;
+
+        if opts.tracks_unknown() {
+            flags |= FLAG_UNKNOWN;
+        }
+
+        if opts.stamp_slots {
+            flags |= FLAG_STAMP;
+        }
+
+        if opts.pending.is_some() {
+            flags |= FLAG_PENDING;
+        }
+
+        if opts.source != SourceKind::Gauge {
+            flags |= FLAG_SOURCE;
+        }
+
+        if opts.journal {
+            flags |= FLAG_JOURNAL;
+        }
+
+        if opts.checksums {
+            flags |= FLAG_CHECKSUM;
+        }
+
         Self {
             magic: RTDB,
+            version: VERSION,
             dp_size: dp.get_size(),
-            dp_hash: dp.get_hash(),
+            dp_hash: dp.get_fingerprint(),
             dp_count: opts.dp_count(),
             t_start: opts.t_start,
             t_step: opts.t_step,
+            heartbeat: opts.heartbeat,
+            gap_policy: opts.gap_policy.to_u32(),
+            flags,
+            archive_count: opts.archive_count as u64,
             t_updated: opts.t_start,
         }
     }
@@ -37,11 +133,19 @@ impl Header {
             return Err(InvalidMagicNumber);
         }
 
+        if self.version == 0 || self.version > VERSION {
+            return Err(UnsupportedVersion(self.version));
+        }
+
+        if self.flags & !KNOWN_FLAGS != 0 {
+            return Err(UnsupportedFlags(self.flags & !KNOWN_FLAGS));
+        }
+
         if self.dp_size != dp.get_size() {
             return Err(InvalidDpSize);
         }
 
-        if !opts.ignore_hash && self.dp_hash != dp.get_hash() {
+        if !opts.ignore_hash && self.dp_hash != self.hash_of(dp) {
             return Err(InvalidDpHash);
         }
 
@@ -53,47 +157,253 @@ impl Header {
             return Err(InvalidDpCount);
         }
 
-        if opts.max_fwd_skip > self.dp_count - 2 {
-            return Err(Error::InvalidSkip);
+        if self.heartbeat != 0 && self.heartbeat < self.t_step {
+            return Err(InvalidHeartbeat);
+        }
+
+        if LongGapPolicy::from_u32(self.gap_policy).is_none() {
+            return Err(InvalidGapPolicy);
+        }
+
+        if self.archive_count > MAX_ARCHIVES as u64 {
+            return Err(Error::InvalidArchive);
         }
 
         Ok(())
     }
 
-    fn check_stream_len<R: Read + Seek>(&self, stream: &mut R) -> Result<()> {
+    fn check_stream_len<R: Read + Seek>(
+        &self,
+        stream: &mut R,
+        primary: &Ring,
+        archive_len: u64,
+    ) -> Result<()> {
         let len = stream.seek(SeekFrom::End(0)).map_err(Error::IoError)?;
         stream.seek(SeekFrom::Start(0)).map_err(Error::IoError)?;
 
-        if self.get_first() > self.t_start {
-            return self.check_full_len(len);
+        if archive_len > 0 || self.get_first() > self.t_start {
+            return self.check_full_len(len, primary.get_end() + archive_len);
         }
 
-        self.check_partial_len(len)
+        self.check_partial_len(len, primary)
     }
 
-    fn check_full_len(&self, len: u64) -> Result<()> {
-        if len != self.dp_count * self.dp_size + self.get_size() {
-            println!("full len {} {}", len, self.dp_count * self.dp_size);
+    fn check_full_len(&self, len: u64, end: u64) -> Result<()> {
+        if len != end {
             return Err(Error::InvalidStreamLen);
         }
 
         Ok(())
     }
 
-    fn check_partial_len(&self, len: u64) -> Result<()> {
+    fn check_partial_len(&self, len: u64, primary: &Ring) -> Result<()> {
         let dps = self.get_slot(self.t_updated) + 1;
 
-        if len < dps * self.dp_size + self.get_size() {
+        if len < primary.get_offset(dps) {
             return Err(Error::InvalidStreamLen);
         }
 
-        if len > self.dp_count * self.dp_size + self.get_size() {
+        if len > primary.get_end() {
             return Err(Error::InvalidStreamLen);
         }
 
         Ok(())
     }
 
+    fn tracks_unknown(&self) -> bool {
+        self.flags & FLAG_UNKNOWN != 0
+    }
+
+    fn stamps_slots(&self) -> bool {
+        self.flags & FLAG_STAMP != 0
+    }
+
+    fn has_pending(&self) -> bool {
+        self.flags & FLAG_PENDING != 0
+    }
+
+    fn get_gap_policy(&self) -> LongGapPolicy {
+        LongGapPolicy::from_u32(self.gap_policy).unwrap_or(LongGapPolicy::Error)
+    }
+
+    fn has_source(&self) -> bool {
+        self.flags & FLAG_SOURCE != 0
+    }
+
+    fn has_journal(&self) -> bool {
+        self.flags & FLAG_JOURNAL != 0
+    }
+
+    fn has_checksums(&self) -> bool {
+        self.flags & FLAG_CHECKSUM != 0
+    }
+
+    /// Tables created before fingerprints were introduced store the legacy
+    /// `get_hash` value, which keeps loading them.
+    fn hash_of<T: DataPoint>(&self, dp: &T) -> u64 {
+        if self.flags & FLAG_FINGERPRINT != 0 {
+            return dp.get_fingerprint();
+        }
+
+        dp.get_hash()
+    }
+
+    fn has_skip(&self) -> bool {
+        self.flags & FLAG_SKIP != 0
+    }
+
+    fn validate_skip(&self, max_skip: u64, mode: FwdSkipMode) -> Result<()> {
+        if max_skip > self.dp_count - 2 {
+            return Err(Error::InvalidSkip);
+        }
+
+        if mode == FwdSkipMode::Unknown && !self.tracks_unknown() {
+            return Err(Error::InvalidSkipMode);
+        }
+
+        Ok(())
+    }
+
+    fn has_schema(&self) -> bool {
+        self.flags & FLAG_SCHEMA != 0
+    }
+
+    /// Reads the schema block that follows the header.
+    fn read_schema<R: Read + Seek>(&self, r: &mut R) -> Result<Option<Schema>> {
+        if !self.has_schema() {
+            return Ok(None);
+        }
+
+        let mut len = 0_u64;
+        len.read_in(r).map_err(Error::IoError)?;
+
+        if len > MAX_SCHEMA_SIZE {
+            return Err(Error::InvalidSchema);
+        }
+
+        let mut block = r.take(len);
+        let schema = Schema::read_in(&mut block).map_err(|_| Error::InvalidSchema)?;
+
+        if block.limit() != 0 || !schema.fits(self.dp_size) || schema.get_size() != self.dp_size {
+            return Err(Error::InvalidSchema);
+        }
+
+        Ok(Some(schema))
+    }
+
+    /// Reads a header of any supported version. Version 1 headers are
+    /// converted and keep `version` set to 1.
+    fn read_stored<R: Read + Seek>(&mut self, r: &mut R) -> std::io::Result<()> {
+        let mut magic = 0_u32;
+        magic.read_in(r)?;
+        r.seek(SeekFrom::Current(-4))?;
+
+        if magic != RTDB_V1 {
+            return self.read_in(r);
+        }
+
+        let mut v1 = LegacyHeader::default();
+        v1.read_in(r)?;
+        *self = Self {
+            magic: RTDB,
+            version: 1,
+            dp_size: v1.dp_size,
+            dp_hash: v1.dp_hash,
+            dp_count: v1.dp_count,
+            t_start: v1.t_start,
+            t_step: v1.t_step,
+            heartbeat: 0,
+            gap_policy: LongGapPolicy::Error.to_u32(),
+            flags: 0,
+            archive_count: 0,
+            t_updated: v1.t_updated,
+        };
+        Ok(())
+    }
+
+    /// Reads the header at the start of `r` and checks that it belongs to a
+    /// table of a supported version, leaving `r` at the end of the header.
+    fn peek<R: Read + Seek>(r: &mut R) -> Result<Self> {
+        let mut header = Header::default();
+        r.seek(SeekFrom::Start(0)).map_err(Error::IoError)?;
+        header.read_stored(r).map_err(Error::IoError)?;
+
+        if header.magic != RTDB {
+            return Err(Error::InvalidMagicNumber);
+        }
+
+        if header.version == 0 || header.version > VERSION {
+            return Err(Error::UnsupportedVersion(header.version));
+        }
+
+        Ok(header)
+    }
+
+    fn info(&self, schema: Option<Schema>, skip: Option<(u64, FwdSkipMode)>) -> TableInfo {
+        TableInfo {
+            magic: if self.version > 1 {
+                self.magic
+            } else {
+                RTDB_V1
+            },
+            version: self.version,
+            dp_size: self.dp_size,
+            dp_hash: self.dp_hash,
+            dp_count: self.dp_count,
+            t_start: self.t_start,
+            t_step: self.t_step,
+            t_updated: self.t_updated,
+            t_first: self.get_first(),
+            heartbeat: self.heartbeat,
+            archive_count: self.archive_count,
+            max_fwd_skip: skip.map(|s| s.0),
+            fwd_skip_mode: skip.map(|s| s.1),
+            schema,
+        }
+    }
+
+    /// Writes the header in the layout of its version.
+    fn write_stored<W: Write + Seek>(&self, w: &mut W) -> std::io::Result<()> {
+        if self.version > 1 {
+            return self.write_out(w);
+        }
+
+        let v1 = LegacyHeader {
+            magic: RTDB_V1,
+            dp_size: self.dp_size,
+            dp_hash: self.dp_hash,
+            dp_count: self.dp_count,
+            t_start: self.t_start,
+            t_step: self.t_step,
+            t_updated: self.t_updated,
+        };
+        v1.write_out(w)
+    }
+
+    fn get_stored_size(&self) -> u64 {
+        if self.version > 1 {
+            return self.get_size();
+        }
+
+        LegacyHeader::default().get_size()
+    }
+
+    fn get_slot_size(&self) -> u64 {
+        let stamp_size = if self.stamps_slots() { 8 } else { 0 };
+        let crc_size = if self.has_checksums() { 4 } else { 0 };
+        self.dp_size + u64::from(self.tracks_unknown()) + stamp_size + crc_size
+    }
+
+    fn ring(&self, offset: u64) -> Ring {
+        Ring {
+            offset,
+            slot_size: self.get_slot_size(),
+            count: self.dp_count,
+            t_start: self.t_start,
+            t_step: self.t_step,
+        }
+    }
+
     fn round_down(&self, t: u64) -> u64 {
         let d = t - self.t_start;
         self.t_start + d - d % self.t_step
@@ -105,8 +415,8 @@ impl Header {
         elapsed % t_total / self.t_step
     }
 
-    fn get_offset(&self, slot: u64) -> u64 {
-        slot * self.dp_size + self.get_size()
+    fn get_index(&self, t: u64) -> u64 {
+        (t - self.t_start) / self.t_step
     }
 
     fn get_first(&self) -> u64 {
@@ -140,249 +450,1949 @@ impl Header {
     }
 }
 
-#[derive(Debug)]
-pub struct Table<T, U>
-where
-    T: DataPoint,
-    U: Read + Write + Seek + Sized,
-{
-    max_skip: u64,
-    skip_mode: FwdSkipMode,
-    header: Header,
-    slot: u64,
-    data: U,
-    dp0: T,
-    dp1: T,
-}
-
-impl<T, U> Table<T, U>
-where
-    T: DataPoint + Copy + Default,
-    U: Read + Write + Seek + Sized,
-{
-    pub fn new(opts: &Options, dp: &T, mut data: U) -> Result<Self> {
-        let header = Header::new(opts, dp);
-        header.validate(opts, dp)?;
-        data.seek(SeekFrom::Start(0)).map_err(Error::IoError)?;
-        header.write_out(&mut data).map_err(Error::IoError)?;
-        dp.write_out(&mut data).map_err(Error::IoError)?;
-        header.check_stream_len(&mut data)?;
+impl ArchiveHeader {
+    fn new(header: &Header, opts: &ArchiveOptions) -> Result<Self> {
+        if !opts.t_step.is_multiple_of(header.t_step) {
+            return Err(Error::InvalidArchive);
+        }
 
         Ok(Self {
-            max_skip: opts.max_fwd_skip,
-            skip_mode: opts.fwd_skip_mode,
-            header,
-            slot: 0,
-            data,
-            dp0: T::default(),
-            dp1: T::default(),
+            cf: opts.cf.to_u32(),
+            xff: opts.xff,
+            steps: opts.t_step / header.t_step,
+            rows: opts.t_total.checked_div(opts.t_step).unwrap_or(0),
+            known: 0,
+            unknown: 0,
         })
     }
 
-    pub fn load(opts: &Options, dp: &T, mut data: U) -> Result<Self> {
-        let mut header = Header::default();
-        data.seek(SeekFrom::Start(0)).map_err(Error::IoError)?;
-        header.read_in(&mut data).map_err(Error::IoError)?;
-        header.validate(opts, dp)?;
-        header.check_stream_len(&mut data)?;
-
-        Ok(Self {
-            max_skip: opts.max_fwd_skip,
-            skip_mode: opts.fwd_skip_mode,
-            header,
-            slot: 0,
-            data,
-            dp0: T::default(),
-            dp1: T::default(),
-        })
-    }
+    fn validate(&self) -> Result<()> {
+        if Consolidation::from_u32(self.cf).is_none() {
+            return Err(Error::InvalidArchive);
+        }
 
-    pub fn insert(&mut self, t_now: u64, dp: &T) -> Result<()> {
-        if t_now <= self.header.t_updated {
-            return Err(Error::UpdateTooEarly);
+        if !(0.0..1.0).contains(&self.xff) {
+            return Err(Error::InvalidArchive);
         }
 
-        let delta = self.header.get_delta(self.header.t_updated, t_now);
+        if self.steps == 0 || self.rows == 0 {
+            return Err(Error::InvalidArchive);
+        }
 
-        match delta {
-            0 => return Err(Error::UpdateTooEarly),
-            1 => {
-                self.seek_to(self.header.t_updated)?;
-                self.seek_fwd()?;
-            }
-            n if n < self.header.dp_count => self.skip_fwd(n, dp)?,
-            _ => return Err(Error::UpdateTooLate),
+        if self.known + self.unknown >= self.steps {
+            return Err(Error::InvalidArchive);
         }
 
-        self.write_out(dp)?;
-        self.update_header(t_now)
+        Ok(())
     }
 
-    pub fn get(&mut self, t: u64) -> Result<&T> {
-        self.header.check_access_time(t)?;
-        self.seek_to(t)?;
-        self.read_in()?;
-        Ok(&self.dp0)
+    fn is_unknown(&self) -> bool {
+        self.known == 0 || self.unknown as f64 > self.xff * self.steps as f64
     }
+}
 
-    pub fn first(&mut self) -> Result<(u64, &T)> {
-        let t = self.header.get_first();
-        self.get(t).map(|v| (t, v))
-    }
+impl PendingHeader {
+    fn validate(&self) -> Result<()> {
+        if Consolidation::from_u32(self.cf).is_none() {
+            return Err(Error::InvalidPending);
+        }
 
-    pub fn last(&mut self) -> Result<(u64, &T)> {
-        let t = self.header.t_updated;
-        self.get(t).map(|v| (t, v))
+        Ok(())
     }
+}
 
-    pub fn iter(&mut self) -> Result<Iter<T, U>> {
-        let now = self.header.get_first();
-        let end = self.header.round_down(self.header.t_updated);
-        self.seek_to(now)?;
-
-        Ok(Iter {
-            table: self,
-            now,
-            end,
-        })
+impl SourceHeader {
+    fn validate(&self) -> Result<()> {
+        match SourceKind::from_u32(self.kind) {
+            Some(SourceKind::Gauge) | None => Err(Error::InvalidSource),
+            Some(_) => Ok(()),
+        }
     }
+}
 
-    pub fn range(&mut self, start: u64, end: u64) -> Result<Iter<T, U>> {
-        self.header.check_access_time(start)?;
-        self.header.check_access_time(end)?;
-        let now = self.header.round_down(start);
-        let end = self.header.round_down(end);
-        self.seek_to(now)?;
-
-        Ok(Iter {
-            table: self,
-            now,
-            end,
-        })
-    }
+/// Summary of a stored table that can be read without knowing the type of
+/// its data points, see `TableInfo::peek` and `Table::info`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableInfo {
+    /// Magic number at the start of the file, which differs for version 1.
+    pub magic: u32,
+    /// On-disk format version, 1 for files without flags or a schema.
+    pub version: u32,
+    pub dp_size: u64,
+    /// Fingerprint of the data point schema, or its `get_hash` value for
+    /// tables created before fingerprints were introduced.
+    pub dp_hash: u64,
+    pub dp_count: u64,
+    pub t_start: u64,
+    pub t_step: u64,
+    /// Time of the last update.
+    pub t_updated: u64,
+    /// Time of the oldest slot still held in the primary ring.
+    pub t_first: u64,
+    pub heartbeat: u64,
+    pub archive_count: u64,
+    /// Stored fwd skip settings, `None` for tables created before they were
+    /// stored.
+    pub max_fwd_skip: Option<u64>,
+    pub fwd_skip_mode: Option<FwdSkipMode>,
+    pub schema: Option<Schema>,
+}
 
-    pub fn into_inner(self) -> U {
-        self.data
-    }
+impl TableInfo {
+    /// Reads the header and schema of the table in `data`, taking the header
+    /// and skip settings of a journaled table from its newest state record.
+    /// Only the fields needed for the summary are checked.
+    pub fn peek<R: Read + Seek>(data: &mut R) -> Result<Self> {
+        let mut header = Header::peek(data)?;
 
-    fn skip_fwd(&mut self, skip: u64, next_dp: &T) -> Result<()> {
-        use FwdSkipMode::*;
+        if header.t_step == 0 {
+            return Err(Error::InvalidTimeStep);
+        }
 
-        if skip > self.max_skip + 1 {
-            return Err(Error::MaxSkipExceeded);
+        if header.dp_count < 2 {
+            return Err(Error::InvalidDpCount);
         }
 
-        self.seek_to(self.header.t_updated)?;
-        self.read_in()?;
+        let schema = header.read_schema(data)?;
+        let mut skip = SkipHeader::peek(&header, data)?;
 
-        for i in 1..skip {
-            match self.skip_mode {
-                DoNothing => self.seek_fwd()?,
-                Linear => self.skip_linear(i, skip, next_dp)?,
-                Nearest => self.skip_nearest(i, skip, next_dp)?,
-                Zeroed => self.skip_default()?,
-            }
+        if header.has_journal() {
+            let schema = schema.as_ref().ok_or(Error::InvalidJournal)?;
+            let (_, state) = Journal::locate(&header, schema).newest(data)?;
+            let mut state = Cursor::new(state);
+            header.read_stored(&mut state).map_err(Error::IoError)?;
+            skip = SkipHeader::peek(&header, &mut state)?;
         }
 
-        Ok(())
-    }
+        if header.t_updated < header.t_start {
+            return Err(Error::InvalidHeader);
+        }
 
-    fn skip_linear(&mut self, i: u64, skip: u64, next_dp: &T) -> Result<()> {
-        self.dp1.lerp(&self.dp0, next_dp, i, skip);
-        self.dp1.write_out(&mut self.data).map_err(Error::IoError)?;
-        self.increment()
+        Ok(header.info(schema, skip))
     }
 
-    fn skip_nearest(&mut self, i: u64, skip: u64, dp: &T) -> Result<()> {
-        if i <= (skip - 1) / 2 {
-            self.write_out_buf()
-        } else {
-            self.write_out(dp)
-        }
+    /// Time span covered by the primary ring, in seconds.
+    pub fn capacity(&self) -> u64 {
+        self.dp_count.saturating_mul(self.t_step)
     }
 
-    fn skip_default(&mut self) -> Result<()> {
-        self.dp0 = T::default();
-        self.write_out_buf()
+    /// Number of slots of the primary ring written since the table was
+    /// created, at most `dp_count`.
+    pub fn filled(&self) -> u64 {
+        let written = (self.t_updated - self.t_start) / self.t_step + 1;
+        written.min(self.dp_count)
     }
 
-    fn increment(&mut self) -> Result<()> {
-        self.slot = (self.slot + 1) % self.header.dp_count;
+    /// Share of the primary ring that has been written, between 0 and 1.
+    pub fn fill_level(&self) -> f64 {
+        self.filled() as f64 / self.dp_count as f64
+    }
+}
 
-        if self.slot == 0 {
-            self.seek_from_start(self.header.get_size())?;
+impl SkipHeader {
+    /// Reads the skip settings that follow the header in `r`, if the table
+    /// stores them.
+    fn peek<R: Read + Seek>(header: &Header, r: &mut R) -> Result<Option<(u64, FwdSkipMode)>> {
+        if !header.has_skip() {
+            return Ok(None);
         }
 
-        Ok(())
+        let mut s = SkipHeader::default();
+        s.read_in(r).map_err(Error::IoError)?;
+        s.validate().map(Some)
     }
 
-    fn seek_from_start(&mut self, offset: u64) -> Result<u64> {
-        self.data
-            .seek(SeekFrom::Start(offset))
-            .map_err(Error::IoError)
+    fn validate(&self) -> Result<(u64, FwdSkipMode)> {
+        match FwdSkipMode::from_u32(self.mode) {
+            Some(mode) => Ok((self.max_skip, mode)),
+            None => Err(Error::InvalidSkipMode),
+        }
     }
+}
 
-    fn seek_to(&mut self, t: u64) -> Result<u64> {
-        self.slot = self.header.get_slot(t);
-        let offset = self.header.get_offset(self.slot);
-        self.seek_from_start(offset)
-    }
+fn mismatch<E: std::fmt::Display, S: std::fmt::Display>(
+    name: &str,
+    expected: E,
+    stored: S,
+) -> Error {
+    let diff = format!("`{}` is {} but {} is stored", name, expected, stored);
+    Error::OptionsMismatch(diff)
+}
 
-    fn seek_fwd(&mut self) -> Result<()> {
-        let ioff = i64::try_from(self.header.dp_size).map_err(|_| Error::IntConvError)?;
-        self.data
-            .seek(SeekFrom::Current(ioff))
-            .map_err(Error::IoError)?;
-        self.increment()
+/// Fails if `opts` sets fwd skip settings that differ from the stored ones.
+fn check_skip(opts: &Options, stored: (u64, FwdSkipMode)) -> Result<()> {
+    match (opts.max_fwd_skip, opts.fwd_skip_mode) {
+        (Some(n), _) if n != stored.0 => Err(mismatch("max_fwd_skip", n, stored.0)),
+        (_, Some(m)) if m != stored.1 => Err(mismatch(
+            "fwd_skip_mode",
+            format!("{:?}", m),
+            format!("{:?}", stored.1),
+        )),
+        _ => Ok(()),
     }
+}
 
-    fn write_out<D: DataPoint>(&mut self, dp: &D) -> Result<()> {
-        dp.write_out(&mut self.data).map_err(Error::IoError)?;
-        self.increment()
+#[derive(Debug, Copy, Clone)]
+struct Ring {
+    offset: u64,
+    slot_size: u64,
+    count: u64,
+    t_start: u64,
+    t_step: u64,
+}
+
+impl Ring {
+    fn round_down(&self, t: u64) -> u64 {
+        let d = t - self.t_start;
+        self.t_start + d - d % self.t_step
     }
 
-    fn write_out_buf(&mut self) -> Result<()> {
-        self.dp0.write_out(&mut self.data).map_err(Error::IoError)?;
-        self.increment()
+    fn get_slot(&self, t: u64) -> u64 {
+        (t - self.t_start) / self.t_step % self.count
     }
 
-    fn read_in(&mut self) -> Result<()> {
-        self.dp0.read_in(&mut self.data).map_err(Error::IoError)?;
-        self.increment()
+    fn get_offset(&self, slot: u64) -> u64 {
+        self.offset + slot * self.slot_size
     }
 
-    fn update_header(&mut self, t_now: u64) -> Result<()> {
-        let offset = self.header.get_size() - self.header.t_updated.get_size();
-        self.seek_from_start(offset)?;
-        t_now.write_out(&mut self.data).map_err(Error::IoError)?;
-        self.header.t_updated = t_now;
-        Ok(())
+    fn get_end(&self) -> u64 {
+        self.get_offset(self.count)
     }
 }
 
-pub struct Iter<'a, T, U>
-where
-    T: DataPoint + Copy + Default,
-    U: Read + Write + Seek + Sized,
-{
-    table: &'a mut Table<T, U>,
-    now: u64,
-    end: u64,
+#[derive(Debug, Copy, Clone)]
+struct Archive<T: Aggregate> {
+    header: ArchiveHeader,
+    cf: Consolidation,
+    acc: T::Acc,
+    ring: Ring,
 }
 
-impl<'a, T, U> Iterator for Iter<'a, T, U>
-where
-    T: DataPoint + Copy + Default,
-    U: Read + Write + Seek + Sized,
-{
-    type Item = (u64, T);
+impl<T: DataPoint + Aggregate + Rate + Clone + Default> Archive<T> {
+    fn feed(&mut self, dp: Option<&T>) {
+        match dp {
+            Some(v) => {
+                v.accumulate(&mut self.acc, 1);
+                self.header.known += 1;
+            }
+            None => self.header.unknown += 1,
+        }
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.now <= self.end {
-            let t = self.now;
-            self.now += self.table.header.t_step;
-            self.table.read_in().ok()?;
-            Some((t, self.table.dp0))
+    fn is_complete(&self) -> bool {
+        self.header.known + self.header.unknown == self.header.steps
+    }
+
+    fn take_row(&mut self, zero: &T) -> Option<T> {
+        let mut row = None;
+
+        if !self.header.is_unknown() {
+            let mut dp = zero.clone();
+            dp.finish(&self.acc, self.cf, self.header.known);
+            row = Some(dp);
+        }
+
+        self.acc = zero.new_acc_like();
+        self.header.known = 0;
+        self.header.unknown = 0;
+        row
+    }
+
+    fn get_span(&self, header: &Header) -> Option<(u64, u64)> {
+        let fed = header.get_index(header.t_updated) + u64::from(!header.has_pending());
+        let done = fed / self.header.steps;
+
+        if done == 0 {
+            return None;
+        }
+
+        let first = done.saturating_sub(self.header.rows);
+        let t_first = self.ring.t_start + first * self.ring.t_step;
+        let t_last = self.ring.t_start + (done - 1) * self.ring.t_step;
+        Some((t_first, t_last))
+    }
+}
+
+#[derive(Debug)]
+struct Pending<T: Aggregate> {
+    header: PendingHeader,
+    cf: Consolidation,
+    acc: T::Acc,
+}
+
+impl<T: DataPoint + Aggregate + Rate + Clone + Default> Pending<T> {
+    fn feed(&mut self, dp: &T, weight: u64) -> T {
+        let weight = match self.cf {
+            Consolidation::Average => weight.max(1),
+            _ => 1,
+        };
+
+        dp.accumulate(&mut self.acc, weight);
+        self.header.weight += weight;
+        let mut res = dp.zeroed();
+        res.finish(&self.acc, self.cf, self.header.weight);
+        res
+    }
+
+    fn take(&mut self, zero: &T) -> Option<T> {
+        let mut res = None;
+
+        if self.header.weight > 0 {
+            let mut dp = zero.clone();
+            dp.finish(&self.acc, self.cf, self.header.weight);
+            res = Some(dp);
+        }
+
+        self.acc = zero.new_acc_like();
+        self.header.weight = 0;
+        res
+    }
+}
+
+#[derive(Debug)]
+struct Source<T> {
+    header: SourceHeader,
+    kind: SourceKind,
+    raw: T,
+}
+
+/// Location of the two alternating state records and the redo record of a
+/// table created with `Options::journal`. Every record is prefixed with a
+/// sequence number and followed by a checksum of both.
+#[derive(Debug, Copy, Clone)]
+struct Journal {
+    offset: u64,
+    state_size: u64,
+    redo_size: u64,
+}
+
+impl Journal {
+    fn new(offset: u64, state_size: u64, dp_size: u64) -> Self {
+        Self {
+            offset,
+            state_size,
+            redo_size: 4 + 8 + dp_size,
+        }
+    }
+
+    /// Finds the journal of a table as `Table::from_parts` lays it out,
+    /// without knowing its data point type. Accumulators are sized like
+    /// those of a `Record` with the stored schema.
+    fn locate(header: &Header, schema: &Schema) -> Self {
+        let acc_size = Record::new(schema).new_acc_like().get_size();
+        let mut state_size = header.get_stored_size();
+
+        if header.has_skip() {
+            state_size += SkipHeader::default().get_size();
+        }
+
+        if header.has_pending() {
+            state_size += PendingHeader::default().get_size() + acc_size;
+        }
+
+        if header.has_source() {
+            state_size += SourceHeader::default().get_size() + header.dp_size;
+        }
+
+        state_size += header.archive_count * (ArchiveHeader::default().get_size() + acc_size);
+        let crc_size = if header.has_checksums() { 8 } else { 0 };
+        let meta_size = state_size + schema_block(schema).len() as u64 + crc_size;
+        Self::new(meta_size, state_size, header.dp_size)
+    }
+
+    fn get_state(&self, seq: u64) -> u64 {
+        self.offset + seq % 2 * (self.state_size + 16)
+    }
+
+    fn get_redo(&self) -> u64 {
+        self.offset + 2 * (self.state_size + 16)
+    }
+
+    fn get_end(&self) -> u64 {
+        self.get_redo() + self.redo_size + 16
+    }
+
+    /// Reads both state records from `r` and returns the sequence number and
+    /// body of the newest intact one.
+    fn newest<R: Read + Seek>(&self, r: &mut R) -> Result<(u64, Vec<u8>)> {
+        let len = usize::try_from(self.state_size + 16).map_err(|_| Error::IntConvError)?;
+        let mut newest: Option<(u64, Vec<u8>)> = None;
+
+        for i in 0..2 {
+            let mut rec = vec![0; len];
+            r.seek(SeekFrom::Start(self.get_state(i)))
+                .map_err(Error::IoError)?;
+            r.read_exact(&mut rec).map_err(Error::IoError)?;
+
+            if let Some((seq, state)) = Journal::open(&rec) {
+                if newest.as_ref().is_none_or(|(s, _)| seq > *s) {
+                    newest = Some((seq, state.to_vec()));
+                }
+            }
+        }
+
+        newest.ok_or(Error::InvalidJournal)
+    }
+
+    fn seal(seq: u64, body: &[u8]) -> Vec<u8> {
+        let mut rec = seq.to_le_bytes().to_vec();
+        rec.extend_from_slice(body);
+        let crc = checksum(&rec);
+        rec.extend_from_slice(&crc.to_le_bytes());
+        rec
+    }
+
+    fn open(rec: &[u8]) -> Option<(u64, &[u8])> {
+        let (data, crc) = rec.split_at(rec.len().checked_sub(8)?);
+        let (seq, body) = data.split_at_checked(8)?;
+
+        if checksum(data).to_le_bytes() != crc {
+            return None;
+        }
+
+        Some((u64::from_le_bytes(seq.try_into().ok()?), body))
+    }
+}
+
+/// Converts an unfinished consolidation of `weight` values to another data
+/// point type by finishing it, mapping the result and starting over with it.
+fn carry<T, V, F>(
+    acc: &T::Acc,
+    cf: Consolidation,
+    weight: u64,
+    zero: (&T, &V),
+    map: &mut F,
+) -> V::Acc
+where
+    T: Aggregate + Clone,
+    V: Aggregate,
+    F: FnMut(&T) -> V,
+{
+    let mut new = zero.1.new_acc_like();
+
+    if weight == 0 {
+        return new;
+    }
+
+    let mut dp = zero.0.clone();
+    dp.finish(acc, cf, weight);
+    let w = if cf == Consolidation::Average {
+        weight
+    } else {
+        1
+    };
+    map(&dp).accumulate(&mut new, w);
+    new
+}
+
+/// Encodes `schema` prefixed with its length, as stored after the header.
+fn schema_block(schema: &Schema) -> Vec<u8> {
+    let mut block = vec![0; 8];
+    _ = schema.write_out(&mut block);
+    let len = block.len() as u64 - 8;
+    block[..8].copy_from_slice(&len.to_le_bytes());
+    block
+}
+
+/// Reads the schema of the table in `data` without knowing the type of its
+/// data points.
+pub(crate) fn stored_schema<U: Read + Seek>(data: &mut U) -> Result<Option<Schema>> {
+    let header = Header::peek(data)?;
+    header.read_schema(data)
+}
+
+/// 64-bit FNV-1a.
+fn checksum(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |h, b| {
+        (h ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// `checksum` folded to 32 bits for slots.
+fn slot_checksum(bytes: &[u8]) -> u32 {
+    let h = checksum(bytes);
+    (h ^ h >> 32) as u32
+}
+
+#[derive(Debug)]
+pub struct Table<T, U>
+where
+    T: DataPoint + Aggregate,
+    U: Read + Write + Seek + Sized,
+{
+    max_skip: u64,
+    skip_mode: FwdSkipMode,
+    header: Header,
+    pending: Option<Pending<T>>,
+    source: Option<Source<T>>,
+    archives: Vec<Archive<T>>,
+    primary: Ring,
+    ring: Ring,
+    slot: u64,
+    data: U,
+    valid: bool,
+    stale: bool,
+    stamp: u64,
+    /// Zero value in the layout of the stored data points, used for
+    /// unknown slots and as the target of consolidations.
+    zero: T,
+    dp0: T,
+    dp1: T,
+    pos: Option<u64>,
+    wbuf: Cursor<Vec<u8>>,
+    rbuf: Vec<u8>,
+    schema: Option<Schema>,
+    schema_size: u64,
+    journal: Option<Journal>,
+    seq: u64,
+    sync: Option<fn(&mut U) -> std::io::Result<()>>,
+}
+
+impl<T, U> Table<T, U>
+where
+    T: DataPoint + Aggregate + Rate + Clone + Default,
+    U: Read + Write + Seek + Sized,
+{
+    pub fn new(opts: &Options, dp: &T, data: U) -> Result<Self> {
+        let mut table = Self::prepare(opts, dp, data)?;
+        let header = table.header;
+        table.write_meta()?;
+        let first = table.source.is_none().then_some(dp);
+        table.seek_to(header.t_start)?;
+        table.write_slot(header.t_start, first)?;
+        table.allocate_archives()?;
+        table.check_stream_len()?;
+
+        match (table.pending.as_mut(), first) {
+            (Some(p), Some(v)) => _ = p.feed(v, 0),
+            (Some(_), None) => {}
+            (None, _) => table.consolidate(header.t_start, first)?,
+        }
+
+        table.update_header(header.t_start)?;
+        Ok(table)
+    }
+
+    /// Length of the stream of a table created with `opts` and `dp` once
+    /// every slot of the primary ring has been written, as checked by
+    /// `Header::check_full_len`.
+    pub(crate) fn full_len(opts: &Options, dp: &T) -> Result<u64> {
+        let table = Table::<T, std::io::Empty>::prepare(opts, dp, std::io::empty())?;
+        Ok(table.primary.get_end() + table.get_archive_len())
+    }
+
+    /// Checks `opts` and lays out a new table in `data` without writing it.
+    fn prepare(opts: &Options, dp: &T, data: U) -> Result<Self> {
+        let header = Header::new(opts, dp);
+        header.validate(opts, dp)?;
+        let skip = opts.skip_or((DEFAULT_MAX_FWD_SKIP, DEFAULT_FWD_SKIP_MODE));
+        header.validate_skip(skip.0, skip.1)?;
+        let pending = opts.pending.map(|cf| {
+            let header = PendingHeader {
+                cf: cf.to_u32(),
+                weight: 0,
+            };
+            (header, dp.new_acc_like())
+        });
+        let source = (opts.source != SourceKind::Gauge).then(|| {
+            let header = SourceHeader {
+                kind: opts.source.to_u32(),
+                t_raw: header.t_start,
+            };
+            (header, dp.clone())
+        });
+        let mut archives = vec![];
+
+        for a in opts.archives() {
+            let archive = ArchiveHeader::new(&header, a)?;
+            archive.validate()?;
+            archives.push((archive, dp.new_acc_like()));
+        }
+
+        let schema = Some(dp.get_schema());
+        let mut table = Self::from_parts(skip, header, pending, source, archives, schema, data);
+        table.set_zero(dp.zeroed());
+        Ok(table)
+    }
+
+    pub fn load(opts: &Options, dp: &T, data: U) -> Result<Self> {
+        Self::open(opts, dp, data, true)
+    }
+
+    /// Loads the table without writing to `data`. Fails with
+    /// `Error::PendingRedo` if a journaled change was interrupted by a crash
+    /// and still needs to be replayed.
+    pub(crate) fn load_read_only(opts: &Options, dp: &T, data: U) -> Result<Self> {
+        Self::open(opts, dp, data, false)
+    }
+
+    fn open(opts: &Options, dp: &T, mut data: U, replay: bool) -> Result<Self> {
+        let mut header = Header::default();
+        data.seek(SeekFrom::Start(0)).map_err(Error::IoError)?;
+        header.read_stored(&mut data).map_err(Error::IoError)?;
+
+        if let Err(e) = header.validate(opts, dp) {
+            return Err(Self::explain(e, &header, dp, &mut data));
+        }
+
+        let schema = header.read_schema(&mut data)?;
+        let mut stored = (DEFAULT_MAX_FWD_SKIP, DEFAULT_FWD_SKIP_MODE);
+
+        if header.has_skip() {
+            let mut s = SkipHeader::default();
+            s.read_in(&mut data).map_err(Error::IoError)?;
+            stored = s.validate()?;
+        }
+
+        if !header.has_skip() {
+            stored = opts.skip_or(stored);
+        }
+
+        let mut pending = None;
+        let mut source = None;
+        let mut archives = vec![];
+
+        if header.has_pending() {
+            let mut p = PendingHeader::default();
+            let mut acc = dp.new_acc_like();
+            p.read_in(&mut data).map_err(Error::IoError)?;
+            acc.read_in(&mut data).map_err(Error::IoError)?;
+            p.validate()?;
+            pending = Some((p, acc));
+        }
+
+        if header.has_source() {
+            let mut s = SourceHeader::default();
+            let mut raw = dp.zeroed();
+            s.read_in(&mut data).map_err(Error::IoError)?;
+            raw.read_in(&mut data).map_err(Error::IoError)?;
+            s.validate()?;
+            source = Some((s, raw));
+        }
+
+        for _ in 0..header.archive_count {
+            let mut archive = ArchiveHeader::default();
+            let mut acc = dp.new_acc_like();
+            archive.read_in(&mut data).map_err(Error::IoError)?;
+            acc.read_in(&mut data).map_err(Error::IoError)?;
+            archive.validate()?;
+            archives.push((archive, acc));
+        }
+
+        let mut table = Self::from_parts(stored, header, pending, source, archives, schema, data);
+        table.set_zero(dp.zeroed());
+        table.check_state()?;
+        table.recover(replay)?;
+        table.check_stream_len()?;
+
+        // The skip settings of a journaled table are only current in the
+        // state restored by `recover`.
+        let stored = (table.max_skip, table.skip_mode);
+        let skip = opts.skip_or(stored);
+        table.header.validate_skip(skip.0, skip.1)?;
+
+        if table.header.has_skip() {
+            check_skip(opts, stored)?;
+        }
+
+        (table.max_skip, table.skip_mode) = skip;
+
+        if opts.strict {
+            table.check_options(opts)?;
+        }
+
+        Ok(table)
+    }
+
+    /// Stores `dp` in the slot of `t_now`. Tables created with
+    /// `Options::pending` also accept updates within the step of the previous
+    /// one and keep their consolidated value in the slot until the step closes.
+    pub fn insert(&mut self, t_now: u64, dp: &T) -> Result<()> {
+        self.check_insert(t_now)?;
+        self.journal(OP_INSERT, t_now, dp)?;
+        self.insert_one(t_now, dp)?;
+        self.update_header(t_now)
+    }
+
+    /// Inserts data points in time order, writing consecutive slots together
+    /// and the header only once at the end. Times are checked to be strictly
+    /// increasing before anything is written. If an element fails, the ones
+    /// before it are kept and its index is returned with the error. Tables
+    /// with a journal commit every element separately.
+    pub fn insert_many<I: IntoIterator<Item = (u64, T)>>(&mut self, items: I) -> Result<()> {
+        let items: Vec<(u64, T)> = items.into_iter().collect();
+        let mut t_prev = self.header.t_updated;
+
+        for (i, (t, _)) in items.iter().enumerate() {
+            if *t <= t_prev {
+                return Err(Error::InsertFailed(i, Box::new(Error::UpdateTooEarly)));
+            }
+
+            t_prev = *t;
+        }
+
+        if self.journal.is_some() {
+            for (i, (t, dp)) in items.iter().enumerate() {
+                self.insert(*t, dp)
+                    .map_err(|e| Error::InsertFailed(i, Box::new(e)))?;
+            }
+
+            return Ok(());
+        }
+
+        for (i, (t, dp)) in items.iter().enumerate() {
+            if let Err(e) = self.insert_one(*t, dp) {
+                self.update_header(self.header.t_updated)?;
+                return Err(Error::InsertFailed(i, Box::new(e)));
+            }
+        }
+
+        self.update_header(self.header.t_updated)
+    }
+
+    /// Runs the checks of `insert_one` that can reject an insert before
+    /// anything is written, so that no redo record is left for it.
+    fn check_insert(&self, t_now: u64) -> Result<()> {
+        if t_now <= self.header.t_updated {
+            return Err(Error::UpdateTooEarly);
+        }
+
+        match self.header.get_delta(self.header.t_updated, t_now) {
+            0 if self.pending.is_none() => Err(Error::UpdateTooEarly),
+            n if n >= self.header.dp_count => match self.header.get_gap_policy() {
+                LongGapPolicy::Error => Err(Error::UpdateTooLate),
+                _ => Ok(()),
+            },
+            n if self.header.heartbeat == 0 && n > self.max_skip + 1 => Err(Error::MaxSkipExceeded),
+            _ => Ok(()),
+        }
+    }
+
+    fn insert_one(&mut self, t_now: u64, dp: &T) -> Result<()> {
+        if t_now <= self.header.t_updated {
+            return Err(Error::UpdateTooEarly);
+        }
+
+        let raw = dp;
+        let mut rate = raw.clone();
+
+        if let Some(s) = self.source.as_ref() {
+            rate.rate(&s.raw, raw, s.kind, t_now - s.header.t_raw);
+        }
+
+        let dp = &rate;
+        let delta = self.header.get_delta(self.header.t_updated, t_now);
+        let t_slot = self.header.round_down(t_now);
+        let heartbeat = self.header.heartbeat;
+        let mode = match t_now - self.header.t_updated {
+            gap if heartbeat > 0 && gap > heartbeat => FwdSkipMode::Unknown,
+            _ => self.skip_mode,
+        };
+
+        match delta {
+            0 if self.pending.is_some() => {
+                self.seek_to(t_now)?;
+            }
+            0 => return Err(Error::UpdateTooEarly),
+            n if n >= self.header.dp_count => match self.header.get_gap_policy() {
+                LongGapPolicy::Error => return Err(Error::UpdateTooLate),
+                LongGapPolicy::Reset => self.reset(t_slot)?,
+                LongGapPolicy::Fill => {
+                    self.close_pending()?;
+                    self.skip_long(t_slot)?;
+                    self.seek_to(t_slot)?;
+                }
+            },
+            n if heartbeat == 0 && n > self.max_skip + 1 => return Err(Error::MaxSkipExceeded),
+            1 => {
+                self.close_pending()?;
+                self.seek_to(t_slot)?;
+            }
+            n => {
+                self.close_pending()?;
+                self.skip_fwd(n, mode, dp)?;
+            }
+        }
+
+        match self.pending.as_mut() {
+            Some(p) => {
+                let since = t_now - self.header.t_updated.max(t_slot);
+                let v = p.feed(dp, since);
+                self.write_slot(t_slot, Some(&v))?;
+            }
+            None => {
+                self.write_slot(t_slot, Some(dp))?;
+                self.consolidate(t_now, Some(dp))?;
+            }
+        }
+
+        if let Some(s) = self.source.as_mut() {
+            s.header.t_raw = t_now;
+            s.raw = raw.clone();
+        }
+
+        self.header.t_updated = t_now;
+        Ok(())
+    }
+
+    /// Overwrites the data point stored for time `t`, which must lie between
+    /// `first()` and `last()`. See `update_range`.
+    pub fn update_at(&mut self, t: u64, dp: &T) -> Result<()> {
+        self.check_range(t, 1)?;
+        self.journal(OP_UPDATE, t, dp)?;
+        self.update_slots(t, std::slice::from_ref(dp))?;
+        self.update_header(self.header.t_updated)
+    }
+
+    /// Overwrites consecutive slots starting with the one for time `start`.
+    /// Values are stored as given, without data source conversion. Archive
+    /// rows whose primary data points are all still in the table are
+    /// consolidated again. Tables with a journal commit every slot
+    /// separately.
+    pub fn update_range(&mut self, start: u64, dps: &[T]) -> Result<()> {
+        if dps.is_empty() {
+            return Ok(());
+        }
+
+        let (start, _) = self.check_range(start, dps.len())?;
+
+        if self.journal.is_some() {
+            for (i, dp) in dps.iter().enumerate() {
+                self.update_at(start + i as u64 * self.header.t_step, dp)?;
+            }
+
+            return Ok(());
+        }
+
+        self.update_slots(start, dps)?;
+        self.update_header(self.header.t_updated)
+    }
+
+    /// Replaces the function used to make written data durable at each
+    /// commit point of a table created with `Options::journal`. Tables opened
+    /// through `create::in_file` or `load::from_file` use `File::sync_data`.
+    pub fn set_sync(&mut self, sync: fn(&mut U) -> std::io::Result<()>) {
+        self.sync = Some(sync);
+    }
+
+    fn check_range(&self, start: u64, len: usize) -> Result<(u64, u64)> {
+        self.header.check_access_time(start)?;
+        let start = self.header.round_down(start);
+        let end = start + (len as u64).saturating_sub(1) * self.header.t_step;
+        self.header.check_access_time(end)?;
+        Ok((start, end))
+    }
+
+    fn update_slots(&mut self, start: u64, dps: &[T]) -> Result<()> {
+        let (start, end) = self.check_range(start, dps.len())?;
+        self.seek_to(start)?;
+
+        for (i, dp) in dps.iter().enumerate() {
+            self.write_slot(start + i as u64 * self.header.t_step, Some(dp))?;
+        }
+
+        let t_last = self.header.round_down(self.header.t_updated);
+
+        if let (Some(p), Some(dp)) = (self.pending.as_mut(), dps.last()) {
+            if end == t_last {
+                p.take(&self.zero);
+                p.feed(dp, 0);
+            }
+        }
+
+        self.reconsolidate(start, end)
+    }
+
+    /// Returns the data point stored for time `t`, or `None` if the slot is
+    /// unknown. Times before `first()` are read from the finest archive that
+    /// still covers them, so the result is then a consolidated row. Fails
+    /// with `Error::StaleSlot` if the table stamps its slots and the slot
+    /// still holds data from an earlier cycle of the ring.
+    pub fn get(&mut self, t: u64) -> Result<Option<&T>> {
+        let (ring, _) = self.find_ring(t)?;
+        self.seek_in(ring, t)?;
+        self.read_in(ring.round_down(t))?;
+
+        if self.stale {
+            return Err(Error::StaleSlot);
+        }
+
+        Ok(self.valid.then_some(&self.dp0))
+    }
+
+    pub fn first(&mut self) -> Result<(u64, Option<&T>)> {
+        let t = self.header.get_first();
+        self.get(t).map(|v| (t, v))
+    }
+
+    pub fn last(&mut self) -> Result<(u64, Option<&T>)> {
+        let t = self.header.t_updated;
+        self.get(t).map(|v| (t, v))
+    }
+
+    pub fn iter(&mut self) -> Result<Iter<'_, T, U>> {
+        let now = self.header.get_first();
+        let end = self.header.round_down(self.header.t_updated);
+        let step = self.header.t_step;
+        self.seek_to(now)?;
+
+        Ok(Iter {
+            table: self,
+            now,
+            end,
+            step,
+            error: None,
+        })
+    }
+
+    /// Iterates over `[start, end]` in the finest resolution that still
+    /// covers `start`, falling back to the consolidated archives once the
+    /// primary data points have been overwritten.
+    pub fn range(&mut self, start: u64, end: u64) -> Result<Iter<'_, T, U>> {
+        self.header.check_access_time(end)?;
+        let (ring, last) = self.find_ring(start)?;
+        let now = ring.round_down(start);
+        let end = ring.round_down(end).min(last);
+        let step = ring.t_step;
+        self.seek_in(ring, now)?;
+
+        Ok(Iter {
+            table: self,
+            now,
+            end,
+            step,
+            error: None,
+        })
+    }
+
+    /// Checks the stored table state and every slot written since the start
+    /// of the primary ring and the archives against their checksums, and
+    /// returns the first mismatch. Tables created without
+    /// `Options::checksums` always pass.
+    pub fn verify(&mut self) -> Result<()> {
+        if !self.header.has_checksums() {
+            return Ok(());
+        }
+
+        self.check_state()?;
+        let t_last = self.header.round_down(self.header.t_updated);
+        let mut spans = vec![(self.primary, self.header.get_first(), t_last)];
+
+        for a in self.archives.iter() {
+            if let Some((start, end)) = a.get_span(&self.header) {
+                spans.push((a.ring, start, end));
+            }
+        }
+
+        for (ring, start, end) in spans {
+            self.seek_in(ring, start)?;
+
+            for i in 0..=(end - start) / ring.t_step {
+                self.read_in(start + i * ring.t_step)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the header fields of the table, as `TableInfo::peek` would
+    /// read them from its stream.
+    pub fn info(&self) -> TableInfo {
+        let skip = self
+            .header
+            .has_skip()
+            .then_some((self.max_skip, self.skip_mode));
+        self.header.info(self.schema.clone(), skip)
+    }
+
+    /// Changes the fwd skip settings set in `opts` and stores them in the
+    /// table. Other options are fixed when the table is created.
+    pub fn tune(&mut self, opts: &Options) -> Result<()> {
+        let (max_skip, mode) = opts.skip_or((self.max_skip, self.skip_mode));
+        self.header.validate_skip(max_skip, mode)?;
+        self.max_skip = max_skip;
+        self.skip_mode = mode;
+
+        if !self.header.has_skip() {
+            return Ok(());
+        }
+
+        self.update_header(self.header.t_updated)
+    }
+
+    /// Fails with `Error::OptionsMismatch` naming the first option in `opts`
+    /// that differs from the ones the table was created with. The start time
+    /// is not compared, since it moves when a long gap resets the table, and
+    /// unset fwd skip settings match any stored ones.
+    pub fn check_options(&self, opts: &Options) -> Result<()> {
+        let h = &self.header;
+        let pending = self.pending.as_ref().map(|p| p.cf);
+        let source = self.source.as_ref().map_or(SourceKind::Gauge, |s| s.kind);
+        let gap_policy = h.get_gap_policy();
+        let diffs = [
+            ("t_step", opts.t_step.to_string(), h.t_step.to_string()),
+            (
+                "dp_count",
+                opts.dp_count().to_string(),
+                h.dp_count.to_string(),
+            ),
+            (
+                "heartbeat",
+                opts.heartbeat.to_string(),
+                h.heartbeat.to_string(),
+            ),
+            (
+                "long_gap_policy",
+                format!("{:?}", opts.gap_policy),
+                format!("{:?}", gap_policy),
+            ),
+            (
+                "track_unknown",
+                opts.tracks_unknown().to_string(),
+                h.tracks_unknown().to_string(),
+            ),
+            (
+                "stamp_slots",
+                opts.stamp_slots.to_string(),
+                h.stamps_slots().to_string(),
+            ),
+            (
+                "pending",
+                format!("{:?}", opts.pending),
+                format!("{:?}", pending),
+            ),
+            (
+                "source",
+                format!("{:?}", opts.source),
+                format!("{:?}", source),
+            ),
+            (
+                "journal",
+                opts.journal.to_string(),
+                h.has_journal().to_string(),
+            ),
+            (
+                "checksums",
+                opts.checksums.to_string(),
+                h.has_checksums().to_string(),
+            ),
+            (
+                "archive count",
+                opts.archives().len().to_string(),
+                self.archives.len().to_string(),
+            ),
+        ];
+
+        if let Some((name, expected, stored)) = diffs.into_iter().find(|(_, e, s)| e != s) {
+            return Err(mismatch(name, expected, stored));
+        }
+
+        for (i, (a, b)) in opts.archives().iter().zip(self.archives.iter()).enumerate() {
+            let rows = a.t_total.checked_div(a.t_step).unwrap_or(0);
+            let expected = format!("{:?}, {}, {}, {} rows", a.cf, a.xff, a.t_step, rows);
+            let stored = format!(
+                "{:?}, {}, {}, {} rows",
+                b.cf, b.header.xff, b.ring.t_step, b.header.rows
+            );
+
+            if expected != stored {
+                return Err(mismatch(&format!("archive {}", i), expected, stored));
+            }
+        }
+
+        check_skip(opts, (self.max_skip, self.skip_mode))
+    }
+
+    /// Returns the layout of the stored data points, if the table was
+    /// created with a schema.
+    pub fn schema(&self) -> Option<&Schema> {
+        self.schema.as_ref()
+    }
+
+    /// Rewrites the table into `data` with data points of type `V`, converting
+    /// every stored data point with `map`, including archive rows and
+    /// unfinished consolidations. The time range, the ring position and
+    /// unknown slots are kept. `schema::by_name` maps fields by name.
+    pub fn migrate<V, W, F>(mut self, opts: &Options, data: W, mut map: F) -> Result<Table<V, W>>
+    where
+        V: DataPoint + Aggregate + Rate + Clone + Default,
+        W: Read + Write + Seek,
+        F: FnMut(&T) -> V,
+    {
+        self.flush_slots()?;
+        self.pos = None;
+        let len = self.data.seek(SeekFrom::End(0)).map_err(Error::IoError)?;
+        let mut header = self.header;
+        header.version = VERSION;
+        header.flags |= FLAG_SCHEMA | FLAG_FINGERPRINT | FLAG_SKIP;
+        let skip = opts.skip_or((self.max_skip, self.skip_mode));
+        header.validate_skip(skip.0, skip.1)?;
+        let zero = map(&self.zero).zeroed();
+        header.dp_size = zero.get_size();
+        header.dp_hash = zero.get_fingerprint();
+        let zeros = (&self.zero, &zero);
+        let pending = self.pending.as_ref().map(|p| {
+            let acc = carry(&p.acc, p.cf, p.header.weight, zeros, &mut map);
+            (p.header, acc)
+        });
+        let source = self.source.as_ref().map(|s| (s.header, map(&s.raw)));
+        let archives = self
+            .archives
+            .iter()
+            .map(|a| {
+                (
+                    a.header,
+                    carry(&a.acc, a.cf, a.header.known, zeros, &mut map),
+                )
+            })
+            .collect();
+        let schema = Some(zero.get_schema());
+        let mut table = Table::from_parts(skip, header, pending, source, archives, schema, data);
+        table.set_zero(zero);
+        table.write_meta()?;
+        let src = std::iter::once(self.primary).chain(self.archives.iter().map(|a| a.ring));
+        let dst = std::iter::once(table.primary).chain(table.archives.iter().map(|a| a.ring));
+        let rings: Vec<_> = src.zip(dst).collect();
+
+        for (src, dst) in rings {
+            let stored = len.saturating_sub(src.offset) / src.slot_size;
+            self.ring = src;
+            self.slot = 0;
+            self.seek_from_start(src.offset)?;
+            table.ring = dst;
+            table.slot = 0;
+            table.seek_from_start(dst.offset)?;
+
+            for _ in 0..stored.min(src.count) {
+                self.read_in(0)?;
+                let dp = self.valid.then(|| map(&self.dp0));
+                table.write_slot(self.stamp, dp.as_ref())?;
+            }
+        }
+
+        table.seq = self.seq;
+        table.update_header(header.t_updated)?;
+        table.check_stream_len()?;
+        Ok(table)
+    }
+
+    /// Returns the on-disk format version of the table.
+    pub fn version(&self) -> u32 {
+        self.header.version
+    }
+
+    /// Copies the table into `data` in the newest format version and returns
+    /// the copy. `opts` is used as in `Table::load`.
+    pub fn upgrade<V: Read + Write + Seek>(
+        mut self,
+        opts: &Options,
+        data: V,
+    ) -> Result<Table<T, V>> {
+        self.flush_slots()?;
+        self.pos = None;
+        let len = self.data.seek(SeekFrom::End(0)).map_err(Error::IoError)?;
+        let mut header = self.header;
+        header.version = VERSION;
+        let pending = self.pending.as_ref().map(|p| (p.header, p.acc.clone()));
+        let source = self.source.as_ref().map(|s| (s.header, s.raw.clone()));
+        let archives = self
+            .archives
+            .iter()
+            .map(|a| (a.header, a.acc.clone()))
+            .collect();
+        header.flags |= FLAG_SCHEMA | FLAG_FINGERPRINT | FLAG_SKIP;
+        header.dp_hash = self.zero.get_fingerprint();
+        let skip = opts.skip_or((self.max_skip, self.skip_mode));
+        header.validate_skip(skip.0, skip.1)?;
+        let schema = Some(self.zero.get_schema());
+        let mut table = Table::from_parts(skip, header, pending, source, archives, schema, data);
+        table.set_zero(self.zero.clone());
+        table.write_meta()?;
+        let src = std::iter::once(self.primary).chain(self.archives.iter().map(|a| a.ring));
+        let dst = std::iter::once(table.primary).chain(table.archives.iter().map(|a| a.ring));
+        let rings: Vec<_> = src.zip(dst).collect();
+
+        for (src, dst) in rings {
+            let end = src.get_end().min(len);
+
+            if end <= src.offset {
+                continue;
+            }
+
+            self.seek_from_start(src.offset)?;
+            table.seek_from_start(dst.offset)?;
+            table.pos = None;
+            let mut ring = (&mut self.data).take(end - src.offset);
+            std::io::copy(&mut ring, &mut table.data).map_err(Error::IoError)?;
+        }
+
+        table.seq = self.seq;
+        table.update_header(header.t_updated)?;
+        table.check_stream_len()?;
+        Ok(table)
+    }
+
+    pub fn into_inner(self) -> U {
+        self.data
+    }
+
+    /// Replaces a size or hash mismatch with the first field that differs
+    /// from the stored schema, if the table has one.
+    fn explain<R: Read + Seek>(e: Error, header: &Header, dp: &T, r: &mut R) -> Error {
+        if !matches!(e, Error::InvalidDpSize | Error::InvalidDpHash) {
+            return e;
+        }
+
+        match header.read_schema(r) {
+            Ok(Some(stored)) => match stored.diff(&dp.get_schema()) {
+                Some(diff) => Error::SchemaMismatch(diff),
+                None => e,
+            },
+            _ => e,
+        }
+    }
+
+    fn from_parts(
+        skip: (u64, FwdSkipMode),
+        header: Header,
+        pending: Option<(PendingHeader, T::Acc)>,
+        source: Option<(SourceHeader, T)>,
+        archives: Vec<(ArchiveHeader, T::Acc)>,
+        schema: Option<Schema>,
+        data: U,
+    ) -> Self {
+        let pending_size = match pending {
+            Some((p, ref acc)) => p.get_size() + acc.get_size(),
+            None => 0,
+        };
+        let archive_size: u64 = archives
+            .iter()
+            .map(|(a, acc)| a.get_size() + acc.get_size())
+            .sum();
+        let source_size = match source {
+            Some((s, ref raw)) => s.get_size() + raw.get_size(),
+            None => 0,
+        };
+        let skip_size = if header.has_skip() {
+            SkipHeader::default().get_size()
+        } else {
+            0
+        };
+        let state_size =
+            header.get_stored_size() + skip_size + pending_size + source_size + archive_size;
+        let schema_size = schema.as_ref().map_or(0, |s| schema_block(s).len() as u64);
+        let crc_size = if header.has_checksums() { 8 } else { 0 };
+        let meta_size = state_size + schema_size + crc_size;
+        let journal = header
+            .has_journal()
+            .then(|| Journal::new(meta_size, state_size, header.dp_size));
+        let primary = header.ring(journal.map_or(meta_size, |j| j.get_end()));
+        let mut offset = primary.get_end();
+        let mut parts = vec![];
+
+        for (archive, acc) in archives {
+            let ring = Ring {
+                offset,
+                slot_size: header.get_slot_size(),
+                count: archive.rows,
+                t_start: header.t_start,
+                t_step: header.t_step * archive.steps,
+            };
+
+            offset = ring.get_end();
+            parts.push(Archive {
+                header: archive,
+                cf: Consolidation::from_u32(archive.cf).unwrap_or(Consolidation::Average),
+                acc,
+                ring,
+            });
+        }
+
+        let pending = pending.map(|(header, acc)| Pending {
+            header,
+            cf: Consolidation::from_u32(header.cf).unwrap_or(Consolidation::Average),
+            acc,
+        });
+        let source = source.map(|(header, raw)| Source {
+            header,
+            kind: SourceKind::from_u32(header.kind).unwrap_or(SourceKind::Gauge),
+            raw,
+        });
+
+        Self {
+            max_skip: skip.0,
+            skip_mode: skip.1,
+            header,
+            pending,
+            source,
+            archives: parts,
+            primary,
+            ring: primary,
+            slot: 0,
+            data,
+            valid: false,
+            stale: false,
+            stamp: 0,
+            zero: T::default(),
+            dp0: T::default(),
+            dp1: T::default(),
+            pos: None,
+            wbuf: Cursor::new(vec![]),
+            rbuf: vec![],
+            schema,
+            schema_size,
+            journal,
+            seq: 0,
+            sync: None,
+        }
+    }
+
+    pub(crate) fn zero(&self) -> &T {
+        &self.zero
+    }
+
+    fn set_zero(&mut self, zero: T) {
+        self.dp0 = zero.clone();
+        self.dp1 = zero.clone();
+        self.zero = zero;
+    }
+
+    fn get_archive_len(&self) -> u64 {
+        self.archives
+            .iter()
+            .map(|a| a.ring.get_end() - a.ring.offset)
+            .sum()
+    }
+
+    fn allocate_archives(&mut self) -> Result<()> {
+        let end = self.primary.get_end() + self.get_archive_len();
+        self.flush_slots()?;
+        self.pos = None;
+        let len = self.data.seek(SeekFrom::End(0)).map_err(Error::IoError)?;
+
+        if self.archives.is_empty() || len >= end {
+            return Ok(());
+        }
+
+        self.write_at(end - 1, &[0])
+    }
+
+    fn check_stream_len(&mut self) -> Result<()> {
+        let archive_len = self.get_archive_len();
+        self.flush_slots()?;
+        self.pos = None;
+        self.header
+            .check_stream_len(&mut self.data, &self.primary, archive_len)
+    }
+
+    fn find_ring(&self, t: u64) -> Result<(Ring, u64)> {
+        match self.header.check_access_time(t) {
+            Ok(()) => Ok((self.primary, self.header.round_down(self.header.t_updated))),
+            Err(Error::OutOfRangePast) => self
+                .archives
+                .iter()
+                .filter_map(|a| {
+                    let (first, last) = a.get_span(&self.header)?;
+                    (t >= first && t < last + a.ring.t_step).then_some((a.ring, last))
+                })
+                .min_by_key(|(ring, _)| ring.t_step)
+                .ok_or(Error::OutOfRangePast),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn consolidate(&mut self, t: u64, dp: Option<&T>) -> Result<()> {
+        let index = self.header.get_index(t);
+        let mut moved = false;
+
+        for i in 0..self.archives.len() {
+            self.archives[i].feed(dp);
+
+            if self.archives[i].is_complete() {
+                let row = self.archives[i].take_row(&self.zero);
+                self.write_row(i, index / self.archives[i].header.steps, row.as_ref())?;
+                moved = true;
+            }
+        }
+
+        if moved {
+            self.seek_to(t + self.header.t_step)?;
+        }
+
+        Ok(())
+    }
+
+    /// Consolidates again the archive rows covering `[start, end]` from the
+    /// primary data points, skipping rows that are partly overwritten.
+    fn reconsolidate(&mut self, start: u64, end: u64) -> Result<()> {
+        let first = self.header.get_index(self.header.get_first());
+        let fed = self.header.get_index(self.header.t_updated) + 1;
+        let fed = fed - u64::from(self.pending.is_some());
+
+        for i in 0..self.archives.len() {
+            let steps = self.archives[i].header.steps;
+            let rows = self.header.get_index(start) / steps..=self.header.get_index(end) / steps;
+
+            for row in rows {
+                let lo = row * steps;
+                let hi = (lo + steps).min(fed);
+
+                if lo < first || lo >= hi {
+                    continue;
+                }
+
+                let mut tmp = Archive {
+                    acc: self.zero.new_acc_like(),
+                    ..self.archives[i]
+                };
+                tmp.header.known = 0;
+                tmp.header.unknown = 0;
+                self.seek_to(self.header.t_start + lo * self.header.t_step)?;
+
+                for index in lo..hi {
+                    self.read_in(self.header.t_start + index * self.header.t_step)?;
+                    let dp = (self.valid && !self.stale).then(|| self.dp0.clone());
+                    tmp.feed(dp.as_ref());
+                }
+
+                if tmp.is_complete() {
+                    let dp = tmp.take_row(&self.zero);
+                    self.write_row(i, row, dp.as_ref())?;
+                } else {
+                    self.archives[i] = tmp;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_row(&mut self, archive: usize, row: u64, dp: Option<&T>) -> Result<()> {
+        let ring = self.archives[archive].ring;
+        let t_row = ring.t_start + row * ring.t_step;
+        self.seek_in(ring, t_row)?;
+        self.write_slot(t_row, dp)
+    }
+
+    /// Marks every step between the last update and `t_slot` as unknown,
+    /// visiting no more slots and rows than the rings can hold.
+    fn skip_long(&mut self, t_slot: u64) -> Result<()> {
+        let first = self.header.get_index(self.header.t_updated) + 1;
+        let last = self.header.get_index(t_slot);
+        let count = (last - first).min(self.header.dp_count);
+        let t_fill = self.header.t_start + (last - count) * self.header.t_step;
+        self.seek_to(t_fill)?;
+
+        for i in 0..count {
+            self.write_slot(t_fill + i * self.header.t_step, None)?;
+        }
+
+        for i in 0..self.archives.len() {
+            let steps = self.archives[i].header.steps;
+            let mut index = first;
+
+            while index < last && !index.is_multiple_of(steps) {
+                self.archives[i].feed(None);
+                index += 1;
+            }
+
+            if self.archives[i].is_complete() {
+                let row = self.archives[i].take_row(&self.zero);
+                self.write_row(i, (index - 1) / steps, row.as_ref())?;
+            }
+
+            let rows = (last - index) / steps;
+            let end = index / steps + rows;
+
+            for row in end - rows.min(self.archives[i].header.rows)..end {
+                self.write_row(i, row, None)?;
+            }
+
+            for _ in index + rows * steps..last {
+                self.archives[i].feed(None);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Restarts the table at `t_slot`, discarding all earlier data points and
+    /// partially consolidated rows.
+    fn reset(&mut self, t_slot: u64) -> Result<()> {
+        self.header.t_start = t_slot;
+        self.header.t_updated = t_slot;
+        self.primary.t_start = t_slot;
+
+        if let Some(p) = self.pending.as_mut() {
+            p.take(&self.zero);
+        }
+
+        for a in self.archives.iter_mut() {
+            a.take_row(&self.zero);
+            a.ring.t_start = t_slot;
+        }
+
+        self.seek_to(t_slot)?;
+        Ok(())
+    }
+
+    fn close_pending(&mut self) -> Result<()> {
+        if let Some(p) = self.pending.as_mut() {
+            let dp = p.take(&self.zero);
+            let t_prev = self.header.round_down(self.header.t_updated);
+            self.consolidate(t_prev, dp.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    fn skip_fwd(&mut self, skip: u64, mode: FwdSkipMode, next_dp: &T) -> Result<()> {
+        use FwdSkipMode::*;
+
+        let t_prev = self.header.round_down(self.header.t_updated);
+        self.seek_to(t_prev)?;
+        self.read_in(t_prev)?;
+        let prev = (self.valid && !self.stale).then(|| self.dp0.clone());
+
+        for i in 1..skip {
+            let t = t_prev + i * self.header.t_step;
+
+            if let DoNothing = mode {
+                // Without stamps, a slot in the first cycle of the ring has
+                // no earlier data to keep and would fail its checksum.
+                let first_cycle = self.header.get_index(t) < self.header.dp_count;
+
+                if self.header.has_checksums() && !self.header.stamps_slots() && first_cycle {
+                    self.write_slot(t, None)?;
+                } else {
+                    self.seek_fwd()?;
+                }
+
+                self.consolidate(t, None)?;
+                continue;
+            }
+
+            let fill = match mode {
+                Linear => prev.as_ref().map(|v| {
+                    self.dp1.lerp(v, next_dp, i, skip);
+                    self.dp1.clone()
+                }),
+                Nearest if i <= (skip - 1) / 2 => prev.clone(),
+                Nearest => Some(next_dp.clone()),
+                Zeroed => Some(self.zero.clone()),
+                DoNothing | Unknown => None,
+            };
+
+            self.write_slot(t, fill.as_ref())?;
+            self.consolidate(t, fill.as_ref())?;
+        }
+
+        Ok(())
+    }
+
+    fn increment(&mut self) -> Result<()> {
+        self.slot = (self.slot + 1) % self.ring.count;
+
+        if self.slot == 0 {
+            self.seek_from_start(self.ring.offset)?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves the cursor to `offset`, touching the stream only if the pending
+    /// slot writes do not already end there.
+    fn seek_from_start(&mut self, offset: u64) -> Result<u64> {
+        if self.pos == Some(offset) {
+            return Ok(offset);
+        }
+
+        self.flush_slots()?;
+        self.pos = Some(offset);
+        self.data
+            .seek(SeekFrom::Start(offset))
+            .map_err(Error::IoError)
+    }
+
+    fn flush_slots(&mut self) -> Result<()> {
+        if !self.wbuf.get_ref().is_empty() {
+            self.data
+                .write_all(self.wbuf.get_ref())
+                .map_err(Error::IoError)?;
+            self.wbuf.get_mut().clear();
+            self.wbuf.set_position(0);
+        }
+
+        Ok(())
+    }
+
+    fn seek_in(&mut self, ring: Ring, t: u64) -> Result<u64> {
+        self.ring = ring;
+        self.slot = ring.get_slot(t);
+        let offset = ring.get_offset(self.slot);
+        self.seek_from_start(offset)
+    }
+
+    fn seek_to(&mut self, t: u64) -> Result<u64> {
+        self.seek_in(self.primary, t)
+    }
+
+    fn seek_fwd(&mut self) -> Result<()> {
+        self.slot = (self.slot + 1) % self.ring.count;
+        self.seek_from_start(self.ring.get_offset(self.slot))?;
+        Ok(())
+    }
+
+    /// Buffers a slot write until the cursor has to move elsewhere.
+    fn write_slot(&mut self, t: u64, dp: Option<&T>) -> Result<()> {
+        let start = self.wbuf.get_ref().len();
+
+        if self.header.stamps_slots() {
+            t.write_out(&mut self.wbuf).map_err(Error::IoError)?;
+        }
+
+        if self.header.tracks_unknown() {
+            let valid = u8::from(dp.is_some());
+            valid.write_out(&mut self.wbuf).map_err(Error::IoError)?;
+        }
+
+        match dp {
+            Some(v) => v.write_out(&mut self.wbuf),
+            None => self.zero.write_out(&mut self.wbuf),
+        }
+        .map_err(Error::IoError)?;
+
+        if self.header.has_checksums() {
+            let crc = slot_checksum(&self.wbuf.get_ref()[start..]);
+            crc.write_out(&mut self.wbuf).map_err(Error::IoError)?;
+        }
+
+        self.pos = self.pos.map(|p| p + self.ring.slot_size);
+        self.increment()
+    }
+
+    /// Reads the slot under the cursor into `dp0`. A slot that is entirely
+    /// zero was never written and passes the checksum test.
+    fn read_in(&mut self, t: u64) -> Result<()> {
+        self.flush_slots()?;
+        let offset = self.ring.get_offset(self.slot);
+        let len = usize::try_from(self.ring.slot_size).map_err(|_| Error::IntConvError)?;
+        let mut slot = std::mem::take(&mut self.rbuf);
+        slot.resize(len, 0);
+        self.data.read_exact(&mut slot).map_err(Error::IoError)?;
+        self.pos = self.pos.map(|p| p + self.ring.slot_size);
+        self.increment()?;
+        let res = self.parse_slot(t, &slot, offset);
+        self.rbuf = slot;
+        res
+    }
+
+    fn parse_slot(&mut self, t: u64, slot: &[u8], offset: u64) -> Result<()> {
+        let mut r = Cursor::new(slot);
+        self.valid = true;
+        self.stale = false;
+        self.stamp = t;
+
+        if self.header.has_checksums() {
+            // A stamped slot that was never written, e.g. one passed over
+            // by `FwdSkipMode::DoNothing`, is all zeros and reads as stale.
+            // Every other slot must match its checksum.
+            let (body, crc) = slot.split_at(slot.len() - 4);
+            let unwritten = self.header.stamps_slots() && t != 0 && slot.iter().all(|b| *b == 0);
+
+            if !unwritten && slot_checksum(body).to_le_bytes() != crc {
+                return Err(Error::ChecksumMismatch(offset));
+            }
+        }
+
+        if self.header.stamps_slots() {
+            self.stamp.read_in(&mut r).map_err(Error::IoError)?;
+            self.stale = self.stamp != t;
+        }
+
+        if self.header.tracks_unknown() {
+            let mut valid = 0_u8;
+            valid.read_in(&mut r).map_err(Error::IoError)?;
+            self.valid = valid != 0;
+        }
+
+        self.dp0.read_in(&mut r).map_err(Error::IoError)
+    }
+
+    fn write_at(&mut self, offset: u64, bytes: &[u8]) -> Result<()> {
+        self.seek_from_start(offset)?;
+        self.pos = None;
+        self.data.write_all(bytes).map_err(Error::IoError)
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        self.flush_slots()?;
+        self.data.flush().map_err(Error::IoError)?;
+
+        if let Some(sync) = self.sync {
+            sync(&mut self.data).map_err(Error::IoError)?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes the header, the fwd skip settings, pending and source state and
+    /// the archive accumulators, in that order.
+    fn write_state<W: Write + Seek>(&self, w: &mut W) -> std::io::Result<()> {
+        self.header.write_stored(w)?;
+
+        if self.header.has_skip() {
+            let skip = SkipHeader {
+                mode: self.skip_mode.to_u32(),
+                max_skip: self.max_skip,
+            };
+            skip.write_out(w)?;
+        }
+
+        if let Some(p) = self.pending.as_ref() {
+            p.header.write_out(w)?;
+            p.acc.write_out(w)?;
+        }
+
+        if let Some(s) = self.source.as_ref() {
+            s.header.write_out(w)?;
+            s.raw.write_out(w)?;
+        }
+
+        for a in self.archives.iter() {
+            a.header.write_out(w)?;
+            a.acc.write_out(w)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_state<R: Read + Seek>(&mut self, r: &mut R) -> std::io::Result<()> {
+        self.header.read_in(r)?;
+
+        if self.header.has_skip() {
+            let mut skip = SkipHeader::default();
+            skip.read_in(r)?;
+            self.skip_mode = FwdSkipMode::from_u32(skip.mode).unwrap_or(self.skip_mode);
+            self.max_skip = skip.max_skip;
+        }
+
+        if let Some(p) = self.pending.as_mut() {
+            p.header.read_in(r)?;
+            p.acc.read_in(r)?;
+        }
+
+        if let Some(s) = self.source.as_mut() {
+            s.header.read_in(r)?;
+            s.raw.read_in(r)?;
+        }
+
+        for a in self.archives.iter_mut() {
+            a.header.read_in(r)?;
+            a.acc.read_in(r)?;
+            a.ring.t_start = self.header.t_start;
+        }
+
+        self.primary.t_start = self.header.t_start;
+        Ok(())
+    }
+
+    /// Serializes the state as stored at the start of the stream, followed by
+    /// its checksum if the table has checksums.
+    fn state_bytes(&self) -> Result<Vec<u8>> {
+        let mut state = Cursor::new(vec![]);
+        self.write_state(&mut state).map_err(Error::IoError)?;
+        let mut state = state.into_inner();
+
+        if self.header.has_checksums() {
+            let crc = checksum(&state);
+            state.extend_from_slice(&crc.to_le_bytes());
+        }
+
+        Ok(state)
+    }
+
+    /// Writes the state around the schema block, which sits between the
+    /// header and the rest of the state.
+    fn write_state_at_start(&mut self) -> Result<()> {
+        let state = self.state_bytes()?;
+        let (header, rest) = state.split_at(self.header.get_stored_size() as usize);
+        self.write_at(0, header)?;
+        self.write_at(header.len() as u64 + self.schema_size, rest)
+    }
+
+    /// Writes the state and the schema block of a new table.
+    fn write_meta(&mut self) -> Result<()> {
+        if let Some(s) = self.schema.as_ref() {
+            let block = schema_block(s);
+            self.write_at(self.header.get_stored_size(), &block)?;
+        }
+
+        self.write_state_at_start()
+    }
+
+    fn check_state(&mut self) -> Result<()> {
+        if !self.header.has_checksums() {
+            return Ok(());
+        }
+
+        let len = self.header.get_stored_size();
+        let mut rec = self.read_record(0, len)?;
+        let rest = self.state_bytes()?.len() as u64 - len;
+        rec.extend(self.read_record(len + self.schema_size, rest)?);
+        let (state, crc) = rec.split_at(rec.len() - 8);
+
+        if checksum(state).to_le_bytes() != crc {
+            return Err(Error::ChecksumMismatch(0));
+        }
+
+        Ok(())
+    }
+
+    fn update_header(&mut self, t_now: u64) -> Result<()> {
+        self.header.t_updated = t_now;
+
+        match self.journal {
+            None => self.write_state_at_start(),
+            Some(journal) => {
+                self.sync()?;
+                self.seq += 1;
+                let mut state = Cursor::new(vec![]);
+                self.write_state(&mut state).map_err(Error::IoError)?;
+                let rec = Journal::seal(self.seq, state.get_ref());
+                self.write_at(journal.get_state(self.seq), &rec)?;
+                self.sync()
+            }
+        }
+    }
+
+    /// Writes a redo record for an insert or update that is about to be
+    /// applied, so that `load` can finish it after a crash.
+    fn journal(&mut self, op: u32, t: u64, dp: &T) -> Result<()> {
+        let Some(journal) = self.journal else {
+            return Ok(());
+        };
+
+        let mut body = Cursor::new(vec![]);
+        op.write_out(&mut body).map_err(Error::IoError)?;
+        t.write_out(&mut body).map_err(Error::IoError)?;
+        dp.write_out(&mut body).map_err(Error::IoError)?;
+        let rec = Journal::seal(self.seq + 1, body.get_ref());
+        self.write_at(journal.get_redo(), &rec)?;
+        self.sync()
+    }
+
+    fn read_record(&mut self, offset: u64, len: u64) -> Result<Vec<u8>> {
+        let len = usize::try_from(len).map_err(|_| Error::IntConvError)?;
+        let mut rec = vec![0; len];
+        self.seek_from_start(offset)?;
+        self.pos = None;
+        self.data.read_exact(&mut rec).map_err(Error::IoError)?;
+        Ok(rec)
+    }
+
+    /// Restores the newest intact state record and replays the redo record
+    /// if it belongs to the next sequence number. Without `replay`, such a
+    /// redo record is an error instead.
+    fn recover(&mut self, replay: bool) -> Result<()> {
+        let Some(journal) = self.journal else {
+            return Ok(());
+        };
+
+        self.flush_slots()?;
+        self.pos = None;
+        let (seq, state) = journal.newest(&mut self.data)?;
+        self.seq = seq;
+        self.read_state(&mut Cursor::new(&state))
+            .map_err(Error::IoError)?;
+
+        let rec = self.read_record(journal.get_redo(), journal.redo_size + 16)?;
+        let redo = match Journal::open(&rec) {
+            Some((s, body)) if s == seq + 1 => body.to_vec(),
+            _ => return Ok(()),
+        };
+
+        if !replay {
+            return Err(Error::PendingRedo);
+        }
+
+        let mut body = Cursor::new(redo);
+        let mut op = 0_u32;
+        let mut t = 0_u64;
+        let mut dp = self.zero.clone();
+        op.read_in(&mut body).map_err(Error::IoError)?;
+        t.read_in(&mut body).map_err(Error::IoError)?;
+        dp.read_in(&mut body).map_err(Error::IoError)?;
+
+        let res = match op {
+            OP_INSERT => self.insert_one(t, &dp),
+            OP_UPDATE => self.update_slots(t, std::slice::from_ref(&dp)),
+            _ => Err(Error::InvalidJournal),
+        };
+
+        match res {
+            Ok(()) => self.update_header(self.header.t_updated),
+            Err(_) => self
+                .read_state(&mut Cursor::new(&state))
+                .map_err(Error::IoError),
+        }
+    }
+}
+
+/// Iterates over the slots of a time range. Iteration ends early at the
+/// first slot that cannot be read; the error is kept in `Iter::error`.
+pub struct Iter<'a, T, U>
+where
+    T: DataPoint + Aggregate + Rate + Clone + Default,
+    U: Read + Write + Seek + Sized,
+{
+    table: &'a mut Table<T, U>,
+    now: u64,
+    end: u64,
+    step: u64,
+    error: Option<Error>,
+}
+
+impl<T, U> Iter<'_, T, U>
+where
+    T: DataPoint + Aggregate + Rate + Clone + Default,
+    U: Read + Write + Seek + Sized,
+{
+    /// Returns the error that ended the iteration, such as
+    /// `Error::ChecksumMismatch` for a damaged slot.
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns the time between the slots of the iteration.
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    pub(crate) fn take_error(&mut self) -> Option<Error> {
+        self.error.take()
+    }
+}
+
+impl<'a, T, U> Iterator for Iter<'a, T, U>
+where
+    T: DataPoint + Aggregate + Rate + Clone + Default,
+    U: Read + Write + Seek + Sized,
+{
+    type Item = (u64, Option<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.now <= self.end && self.error.is_none() {
+            let t = self.now;
+            self.now += self.step;
+
+            if let Err(e) = self.table.read_in(t) {
+                self.error = Some(e);
+                return None;
+            }
+
+            let known = self.table.valid && !self.table.stale;
+            Some((t, known.then(|| self.table.dp0.clone())))
         } else {
             None
         }
@@ -0,0 +1,646 @@
+use super::data::DataPoint;
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{Cursor, Read, Write};
+
+const MAX_DEPTH: usize = 32;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Primitive {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    I64,
+    U64,
+    I128,
+    U128,
+    F32,
+    F64,
+    Bool,
+    Char,
+}
+
+impl Primitive {
+    pub fn get_size(self) -> u64 {
+        use Primitive::*;
+        match self {
+            I8 | U8 | Bool => 1,
+            I16 | U16 => 2,
+            I32 | U32 | F32 | Char => 4,
+            I64 | U64 | F64 => 8,
+            I128 | U128 => 16,
+        }
+    }
+
+    pub(crate) fn to_u32(self) -> u32 {
+        use Primitive::*;
+        match self {
+            I8 => 0,
+            U8 => 1,
+            I16 => 2,
+            U16 => 3,
+            I32 => 4,
+            U32 => 5,
+            I64 => 6,
+            U64 => 7,
+            I128 => 8,
+            U128 => 9,
+            F32 => 10,
+            F64 => 11,
+            Bool => 12,
+            Char => 13,
+        }
+    }
+
+    pub(crate) fn from_u32(val: u32) -> Option<Self> {
+        use Primitive::*;
+        match val {
+            0 => Some(I8),
+            1 => Some(U8),
+            2 => Some(I16),
+            3 => Some(U16),
+            4 => Some(I32),
+            5 => Some(U32),
+            6 => Some(I64),
+            7 => Some(U64),
+            8 => Some(I128),
+            9 => Some(U128),
+            10 => Some(F32),
+            11 => Some(F64),
+            12 => Some(Bool),
+            13 => Some(Char),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Primitive {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Primitive::*;
+        let name = match self {
+            I8 => "i8",
+            U8 => "u8",
+            I16 => "i16",
+            U16 => "u16",
+            I32 => "i32",
+            U32 => "u32",
+            I64 => "i64",
+            U64 => "u64",
+            I128 => "i128",
+            U128 => "u128",
+            F32 => "f32",
+            F64 => "f64",
+            Bool => "bool",
+            Char => "char",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Layout of a data point as stored in a table, generated by the
+/// `datapoint!` macro and written into every new table. Tuples are structs
+/// with an empty name and fields named `0`, `1` and so on, and enums are
+/// stored as the `u32` index of their variant. Options are stored as a
+/// `bool` presence flag named `some` followed by their `value`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Schema {
+    Primitive(Primitive),
+    Array(Box<Schema>, u64),
+    Struct(String, Vec<(String, Schema)>),
+    Enum(String, Vec<String>),
+    Option(Box<Schema>),
+}
+
+/// A primitive value within a stored data point, with its path from the
+/// top-level data point and its byte offset in the slot.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Leaf {
+    pub path: String,
+    pub kind: Primitive,
+    pub offset: u64,
+}
+
+impl Schema {
+    pub fn get_size(&self) -> u64 {
+        match self {
+            Schema::Primitive(p) => p.get_size(),
+            Schema::Array(elem, len) => elem.get_size().saturating_mul(*len),
+            Schema::Struct(_, fields) => fields
+                .iter()
+                .fold(0, |n, (_, s)| n.saturating_add(s.get_size())),
+            Schema::Enum(..) => Primitive::U32.get_size(),
+            Schema::Option(inner) => inner.get_size().saturating_add(1),
+        }
+    }
+
+    /// Whether every array fits in `size` bytes, counting elements without
+    /// data as one byte. Nested arrays share the size of their parent, so
+    /// walking a schema that fits never visits more than `size` elements.
+    pub(crate) fn fits(&self, size: u64) -> bool {
+        match self {
+            Schema::Primitive(p) => p.get_size() <= size,
+            Schema::Array(_, 0) => true,
+            Schema::Array(elem, len) => {
+                len.checked_mul(elem.get_size().max(1))
+                    .is_some_and(|n| n <= size)
+                    && elem.fits(size / len)
+            }
+            Schema::Struct(_, fields) => fields.iter().all(|(_, s)| s.fits(size)),
+            Schema::Enum(..) => Primitive::U32.get_size() <= size,
+            Schema::Option(inner) => inner.fits(size),
+        }
+    }
+
+    /// Lists the primitive values of the data point in storage order, which
+    /// is enough to decode a slot without the Rust type.
+    pub fn leaves(&self) -> Vec<Leaf> {
+        let mut leaves = vec![];
+        self.push_leaves("", &mut 0, &mut leaves);
+        leaves
+    }
+
+    fn push_leaves(&self, path: &str, offset: &mut u64, leaves: &mut Vec<Leaf>) {
+        match self {
+            Schema::Primitive(kind) => {
+                leaves.push(Leaf {
+                    path: path.to_string(),
+                    kind: *kind,
+                    offset: *offset,
+                });
+                *offset += kind.get_size();
+            }
+            Schema::Enum(..) => Schema::Primitive(Primitive::U32).push_leaves(path, offset, leaves),
+            Schema::Array(elem, len) => {
+                for i in 0..*len {
+                    elem.push_leaves(&format!("{}[{}]", path, i), offset, leaves);
+                }
+            }
+            Schema::Struct(_, fields) => {
+                for (name, field) in fields.iter() {
+                    let path = match path {
+                        "" => name.clone(),
+                        _ => format!("{}.{}", path, name),
+                    };
+                    field.push_leaves(&path, offset, leaves);
+                }
+            }
+            Schema::Option(inner) => Self::as_struct(inner).push_leaves(path, offset, leaves),
+        }
+    }
+
+    /// Describes the first primitive value whose type or path differs
+    /// between the stored schema `self` and `other`, or `None` if they store
+    /// the same primitive values under the same names.
+    pub fn diff(&self, other: &Schema) -> Option<String> {
+        let stored = self.leaves();
+        let expected = other.leaves();
+
+        for (s, e) in stored.iter().zip(expected.iter()) {
+            if s.kind != e.kind {
+                return Some(format!(
+                    "{} is stored as {} but {} is {}",
+                    label(&s.path),
+                    s.kind,
+                    label(&e.path),
+                    e.kind
+                ));
+            }
+
+            if s.path != e.path {
+                return Some(format!(
+                    "{} is stored as {}",
+                    label(&e.path),
+                    label(&s.path)
+                ));
+            }
+        }
+
+        match stored.len().cmp(&expected.len()) {
+            std::cmp::Ordering::Less => {
+                let e = &expected[stored.len()];
+                Some(format!("{} is not stored", label(&e.path)))
+            }
+            std::cmp::Ordering::Greater => {
+                let s = &stored[expected.len()];
+                Some(format!(
+                    "stored {} is not in the data point",
+                    label(&s.path)
+                ))
+            }
+            std::cmp::Ordering::Equal => {
+                let stored = self.enums();
+                let expected = other.enums();
+                let (s, e) = stored.iter().zip(expected.iter()).find(|(s, e)| s != e)?;
+                Some(format!(
+                    "{} has variants {} but the stored ones are {}",
+                    label(&e.0),
+                    e.1.join(", "),
+                    s.1.join(", ")
+                ))
+            }
+        }
+    }
+
+    /// Lists the paths and variants of the enums in the data point.
+    fn enums(&self) -> Vec<(String, &[String])> {
+        let mut enums = vec![];
+        self.push_enums(String::new(), &mut enums);
+        enums
+    }
+
+    fn push_enums<'a>(&'a self, path: String, enums: &mut Vec<(String, &'a [String])>) {
+        match self {
+            Schema::Primitive(_) => (),
+            Schema::Enum(_, variants) => enums.push((path, variants)),
+            Schema::Array(elem, len) => {
+                for i in 0..*len {
+                    elem.push_enums(format!("{}[{}]", path, i), enums);
+                }
+            }
+            Schema::Struct(_, fields) => {
+                for (name, field) in fields.iter() {
+                    let path = match path.as_str() {
+                        "" => name.clone(),
+                        _ => format!("{}.{}", path, name),
+                    };
+                    field.push_enums(path, enums);
+                }
+            }
+            Schema::Option(inner) => {
+                let path = match path.as_str() {
+                    "" => "value".to_string(),
+                    _ => format!("{}.value", path),
+                };
+                inner.push_enums(path, enums);
+            }
+        }
+    }
+
+    /// FNV-1a hash over field names, primitive types, array lengths and
+    /// nesting. Struct type names are left out, so renaming a type keeps
+    /// its tables loadable.
+    pub fn fingerprint(&self) -> u64 {
+        let mut bytes = vec![];
+        self.push_canonical(&mut bytes);
+        bytes.iter().fold(0xcbf29ce484222325, |h, b| {
+            (h ^ u64::from(*b)).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    fn push_canonical(&self, bytes: &mut Vec<u8>) {
+        match self {
+            Schema::Primitive(p) => {
+                bytes.push(0);
+                bytes.extend_from_slice(&p.to_u32().to_le_bytes());
+            }
+            Schema::Array(elem, len) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&len.to_le_bytes());
+                elem.push_canonical(bytes);
+            }
+            Schema::Struct(_, fields) => {
+                bytes.push(2);
+                bytes.extend_from_slice(&(fields.len() as u32).to_le_bytes());
+
+                for (name, field) in fields.iter() {
+                    bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(name.as_bytes());
+                    field.push_canonical(bytes);
+                }
+            }
+            Schema::Enum(_, variants) => {
+                bytes.push(3);
+                bytes.extend_from_slice(&(variants.len() as u32).to_le_bytes());
+
+                for name in variants.iter() {
+                    bytes.extend_from_slice(&(name.len() as u32).to_le_bytes());
+                    bytes.extend_from_slice(name.as_bytes());
+                }
+            }
+            Schema::Option(inner) => Self::as_struct(inner).push_canonical(bytes),
+        }
+    }
+
+    /// The struct an option is laid out as, which keeps its leaf paths and
+    /// fingerprint the same as a struct with the same fields.
+    fn as_struct(inner: &Schema) -> Schema {
+        Schema::Struct(
+            String::new(),
+            vec![
+                ("some".to_string(), Schema::Primitive(Primitive::Bool)),
+                ("value".to_string(), inner.clone()),
+            ],
+        )
+    }
+
+    pub(crate) fn write_out<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        match self {
+            Schema::Primitive(p) => {
+                writer.write_all(&[0])?;
+                writer.write_all(&p.to_u32().to_le_bytes())
+            }
+            Schema::Array(elem, len) => {
+                writer.write_all(&[1])?;
+                writer.write_all(&len.to_le_bytes())?;
+                elem.write_out(writer)
+            }
+            Schema::Struct(name, fields) => {
+                writer.write_all(&[2])?;
+                write_str(writer, name)?;
+                writer.write_all(&(fields.len() as u32).to_le_bytes())?;
+
+                for (name, field) in fields.iter() {
+                    write_str(writer, name)?;
+                    field.write_out(writer)?;
+                }
+
+                Ok(())
+            }
+            Schema::Enum(name, variants) => {
+                writer.write_all(&[3])?;
+                write_str(writer, name)?;
+                writer.write_all(&(variants.len() as u32).to_le_bytes())?;
+
+                for name in variants.iter() {
+                    write_str(writer, name)?;
+                }
+
+                Ok(())
+            }
+            Schema::Option(inner) => {
+                writer.write_all(&[4])?;
+                inner.write_out(writer)
+            }
+        }
+    }
+
+    pub(crate) fn read_in<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        Self::read_nested(reader, 0)
+    }
+
+    fn read_nested<R: Read>(reader: &mut R, depth: usize) -> std::io::Result<Self> {
+        if depth > MAX_DEPTH {
+            return Err(invalid("schema is nested too deeply"));
+        }
+
+        let mut tag = [0];
+        reader.read_exact(&mut tag)?;
+
+        match tag[0] {
+            0 => Primitive::from_u32(read_u32(reader)?)
+                .map(Schema::Primitive)
+                .ok_or_else(|| invalid("unknown primitive type")),
+            1 => {
+                let mut len = [0; 8];
+                reader.read_exact(&mut len)?;
+                let elem = Self::read_nested(reader, depth + 1)?;
+                Ok(Schema::Array(Box::new(elem), u64::from_le_bytes(len)))
+            }
+            2 => {
+                let name = read_str(reader)?;
+                let count = read_u32(reader)?;
+                let mut fields = vec![];
+
+                for _ in 0..count {
+                    let name = read_str(reader)?;
+                    fields.push((name, Self::read_nested(reader, depth + 1)?));
+                }
+
+                Ok(Schema::Struct(name, fields))
+            }
+            3 => {
+                let name = read_str(reader)?;
+                let count = read_u32(reader)?;
+                let mut variants = vec![];
+
+                for _ in 0..count {
+                    variants.push(read_str(reader)?);
+                }
+
+                Ok(Schema::Enum(name, variants))
+            }
+            4 => {
+                let inner = Self::read_nested(reader, depth + 1)?;
+                Ok(Schema::Option(Box::new(inner)))
+            }
+            _ => Err(invalid("unknown schema tag")),
+        }
+    }
+}
+
+impl fmt::Display for Schema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Schema::Primitive(p) => p.fmt(f),
+            Schema::Array(elem, len) => write!(f, "[{}; {}]", elem, len),
+            Schema::Struct(name, fields) if name.is_empty() => {
+                write!(f, "(")?;
+
+                for (i, (_, field)) in fields.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { ", " };
+                    write!(f, "{}{}", sep, field)?;
+                }
+
+                let comma = if fields.len() == 1 { "," } else { "" };
+                write!(f, "{})", comma)
+            }
+            Schema::Struct(name, fields) => {
+                write!(f, "{} {{ ", name)?;
+
+                for (i, (name, field)) in fields.iter().enumerate() {
+                    let sep = if i == 0 { "" } else { ", " };
+                    write!(f, "{}{}: {}", sep, name, field)?;
+                }
+
+                write!(f, " }}")
+            }
+            Schema::Enum(name, variants) => {
+                write!(f, "enum {} {{ {} }}", name, variants.join(", "))
+            }
+            Schema::Option(inner) => write!(f, "Option<{}>", inner),
+        }
+    }
+}
+
+/// Returns a mapping for `Table::migrate` that copies every field of `T` to
+/// the field of `V` with the same path, converting between primitive types
+/// and saturating at the bounds of the new type. Fields only in `V` are
+/// zero and fields only in `T` are dropped.
+pub fn by_name<T: DataPoint + Default, V: DataPoint + Default>() -> impl FnMut(&T) -> V {
+    let old: HashMap<String, Leaf> = T::default()
+        .get_schema()
+        .leaves()
+        .into_iter()
+        .map(|l| (l.path.clone(), l))
+        .collect();
+    let pairs: Vec<(Leaf, Leaf)> = V::default()
+        .get_schema()
+        .leaves()
+        .into_iter()
+        .filter_map(|l| Some((old.get(&l.path)?.clone(), l)))
+        .collect();
+    let size = V::default().get_size() as usize;
+
+    move |dp| {
+        let mut src = Cursor::new(vec![]);
+        let mut dst = vec![0; size];
+        let mut res = V::default();
+
+        if dp.write_out(&mut src).is_ok() {
+            let src = src.into_inner();
+
+            for (from, to) in pairs.iter() {
+                let v = Value::read(from.kind, &src[from.offset as usize..]);
+                v.write(to.kind, &mut dst[to.offset as usize..]);
+            }
+
+            _ = res.read_in(&mut Cursor::new(dst));
+        }
+
+        res
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+enum Value {
+    Int(i128),
+    UInt(u128),
+    Float(f64),
+}
+
+macro_rules! _internal_le {
+    ($type:ty, $bytes:expr) => {{
+        let mut buf = [0; std::mem::size_of::<$type>()];
+        buf.copy_from_slice(&$bytes[..std::mem::size_of::<$type>()]);
+        <$type>::from_le_bytes(buf)
+    }};
+}
+
+impl Value {
+    fn read(kind: Primitive, b: &[u8]) -> Self {
+        use Primitive::*;
+        use Value::*;
+        match kind {
+            I8 => Int(_internal_le!(i8, b).into()),
+            U8 => UInt(_internal_le!(u8, b).into()),
+            I16 => Int(_internal_le!(i16, b).into()),
+            U16 => UInt(_internal_le!(u16, b).into()),
+            I32 => Int(_internal_le!(i32, b).into()),
+            U32 => UInt(_internal_le!(u32, b).into()),
+            I64 => Int(_internal_le!(i64, b).into()),
+            U64 => UInt(_internal_le!(u64, b).into()),
+            I128 => Int(_internal_le!(i128, b)),
+            U128 => UInt(_internal_le!(u128, b)),
+            F32 => Float(_internal_le!(f32, b).into()),
+            F64 => Float(_internal_le!(f64, b)),
+            Bool => UInt((b[0] != 0).into()),
+            Char => UInt(_internal_le!(u32, b).into()),
+        }
+    }
+
+    /// Clamps the value to `[lo, hi]`, with `NaN` becoming zero.
+    fn to_int(self, lo: i128, hi: i128) -> i128 {
+        match self {
+            Value::Int(i) => i.clamp(lo, hi),
+            Value::UInt(u) => i128::try_from(u).unwrap_or(i128::MAX).clamp(lo, hi),
+            Value::Float(f) => (f as i128).clamp(lo, hi),
+        }
+    }
+
+    fn to_u128(self) -> u128 {
+        match self {
+            Value::Int(i) => u128::try_from(i).unwrap_or(0),
+            Value::UInt(u) => u,
+            Value::Float(f) => f as u128,
+        }
+    }
+
+    fn to_f64(self) -> f64 {
+        match self {
+            Value::Int(i) => i as f64,
+            Value::UInt(u) => u as f64,
+            Value::Float(f) => f,
+        }
+    }
+
+    fn to_bool(self) -> bool {
+        match self {
+            Value::Int(i) => i != 0,
+            Value::UInt(u) => u != 0,
+            Value::Float(f) => f != 0.0,
+        }
+    }
+
+    /// Code points that are not valid characters become `'\0'`.
+    fn to_char(self) -> char {
+        let code = self.to_int(0, u32::MAX.into()) as u32;
+        char::from_u32(code).unwrap_or('\0')
+    }
+
+    fn write(self, kind: Primitive, out: &mut [u8]) {
+        use Primitive::*;
+
+        macro_rules! int {
+            ($type:ty) => {
+                (self.to_int(<$type>::MIN as i128, <$type>::MAX as i128) as $type).to_le_bytes()
+            };
+        }
+
+        let n = kind.get_size() as usize;
+        match kind {
+            I8 => out[..n].copy_from_slice(&int!(i8)),
+            U8 => out[..n].copy_from_slice(&int!(u8)),
+            I16 => out[..n].copy_from_slice(&int!(i16)),
+            U16 => out[..n].copy_from_slice(&int!(u16)),
+            I32 => out[..n].copy_from_slice(&int!(i32)),
+            U32 => out[..n].copy_from_slice(&int!(u32)),
+            I64 => out[..n].copy_from_slice(&int!(i64)),
+            U64 => out[..n].copy_from_slice(&int!(u64)),
+            I128 => out[..n].copy_from_slice(&int!(i128)),
+            U128 => out[..n].copy_from_slice(&self.to_u128().to_le_bytes()),
+            F32 => out[..n].copy_from_slice(&(self.to_f64() as f32).to_le_bytes()),
+            F64 => out[..n].copy_from_slice(&self.to_f64().to_le_bytes()),
+            Bool => out[0] = self.to_bool().into(),
+            Char => out[..n].copy_from_slice(&u32::from(self.to_char()).to_le_bytes()),
+        }
+    }
+}
+
+/// Names a leaf or enum path in `Schema::diff` messages. A data point that
+/// is itself a primitive or an enum has an empty path.
+fn label(path: &str) -> String {
+    match path {
+        "" => "data point".to_string(),
+        path => format!("`{}`", path),
+    }
+}
+
+fn invalid(msg: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, msg)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    let mut buf = [0; 4];
+    reader.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn write_str<W: Write>(writer: &mut W, s: &str) -> std::io::Result<()> {
+    writer.write_all(&(s.len() as u32).to_le_bytes())?;
+    writer.write_all(s.as_bytes())
+}
+
+fn read_str<R: Read>(reader: &mut R) -> std::io::Result<String> {
+    let len = read_u32(reader)? as u64;
+    let mut buf = vec![];
+    reader.take(len).read_to_end(&mut buf)?;
+
+    if buf.len() as u64 != len {
+        return Err(invalid("truncated schema"));
+    }
+
+    String::from_utf8(buf).map_err(|_| invalid("schema name is not UTF-8"))
+}
@@ -0,0 +1,122 @@
+use roundtable::prelude::*;
+
+fn aggregate<T: Aggregate + Default>(values: &[T], cf: Consolidation) -> T {
+    let mut acc = T::new_acc();
+
+    for v in values {
+        v.accumulate(&mut acc, 1);
+    }
+
+    let mut res = T::default();
+    res.finish(&acc, cf, values.len() as u64);
+    res
+}
+
+#[test]
+fn primitives() {
+    let values = [200_u8, 250, 255, 3];
+    assert_eq!(aggregate(&values, Consolidation::Average), 177);
+    assert_eq!(aggregate(&values, Consolidation::Min), 3);
+    assert_eq!(aggregate(&values, Consolidation::Max), 255);
+    assert_eq!(aggregate(&values, Consolidation::Last), 3);
+    assert_eq!(aggregate(&values, Consolidation::Sum), 255);
+
+    let values = [-1.5_f32, 2.5, 5.0];
+    assert_eq!(aggregate(&values, Consolidation::Average), 2.0);
+    assert_eq!(aggregate(&values, Consolidation::Min), -1.5);
+    assert_eq!(aggregate(&values, Consolidation::Max), 5.0);
+    assert_eq!(aggregate(&values, Consolidation::Sum), 6.0);
+
+    let values = [i64::MIN, -1];
+    assert_eq!(aggregate(&values, Consolidation::Sum), i64::MIN);
+    assert_eq!(aggregate(&values, Consolidation::Average), i64::MIN / 2);
+}
+
+#[test]
+fn arrays() {
+    let values = [[1_i16, -4], [3, -8], [-10, 0]];
+    assert_eq!(aggregate(&values, Consolidation::Average), [-2, -4]);
+    assert_eq!(aggregate(&values, Consolidation::Min), [-10, -8]);
+    assert_eq!(aggregate(&values, Consolidation::Max), [3, 0]);
+    assert_eq!(aggregate(&values, Consolidation::Last), [-10, 0]);
+}
+
+#[test]
+fn structs() {
+    roundtable::datapoint! {
+        struct MemInfo {
+            total: u32,
+            free: u32,
+            avail: u32,
+            buffers: u32,
+            cached: u32,
+        }
+
+        struct Nested {
+            weight: f64,
+            acc: [u8; 2],
+            mem: MemInfo,
+        }
+
+        struct Empty {}
+    }
+
+    let a = MemInfo {
+        total: 4000000000,
+        free: 1000,
+        avail: 3000,
+        buffers: 10,
+        cached: 20,
+    };
+    let b = MemInfo {
+        total: 4000000000,
+        free: 3000,
+        avail: 1000,
+        buffers: 30,
+        cached: 0,
+    };
+    let mean = MemInfo {
+        total: 4000000000,
+        free: 2000,
+        avail: 2000,
+        buffers: 20,
+        cached: 10,
+    };
+    assert_eq!(aggregate(&[a, b], Consolidation::Average), mean);
+
+    let values = [
+        Nested {
+            weight: 1.0,
+            acc: [1, 9],
+            mem: a,
+        },
+        Nested {
+            weight: 3.0,
+            acc: [5, 7],
+            mem: b,
+        },
+    ];
+    let max = aggregate(&values, Consolidation::Max);
+    assert_eq!(max.weight, 3.0);
+    assert_eq!(max.acc, [5, 9]);
+    assert_eq!(max.mem.free, 3000);
+    assert_eq!(max.mem.avail, 3000);
+    assert_eq!(aggregate(&[Empty {}], Consolidation::Sum), Empty {});
+}
+
+#[test]
+fn merge() {
+    let mut left = <[u64; 2]>::new_acc();
+    let mut right = <[u64; 2]>::new_acc();
+    [1_u64, 10].accumulate(&mut left, 1);
+    [2_u64, 20].accumulate(&mut left, 1);
+    [6_u64, 60].accumulate(&mut right, 2);
+    <[u64; 2]>::merge(&mut left, &right);
+    let mut res = [0_u64; 2];
+    res.finish(&left, Consolidation::Average, 4);
+    assert_eq!(res, [3, 37]);
+    res.finish(&left, Consolidation::Last, 4);
+    assert_eq!(res, [6, 60]);
+    res.finish(&left, Consolidation::Sum, 4);
+    assert_eq!(res, [15, 150]);
+}
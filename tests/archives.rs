@@ -0,0 +1,224 @@
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::rtdb::Table;
+use std::io::Cursor;
+
+#[test]
+fn consolidation() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 40)
+        .archive(Consolidation::Average, 0.5, 20, 200)
+        .archive(Consolidation::Max, 0.5, 40, 400);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+
+    for i in 1..11 {
+        t.insert(i * 10, &(i as i32 * 10)).unwrap();
+    }
+
+    assert_eq!(t.first().unwrap(), (70, Some(&70)));
+    assert_eq!(t.get(75).unwrap(), Some(&70));
+    assert_eq!(t.get(50).unwrap(), Some(&45));
+    assert_eq!(t.get(10).unwrap(), Some(&5));
+    let rows: Vec<_> = t.range(0, 100).unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![
+            (0, Some(5)),
+            (20, Some(25)),
+            (40, Some(45)),
+            (60, Some(65)),
+            (80, Some(85))
+        ]
+    );
+
+    let buf2 = t.into_inner();
+    let mut t2 = Table::load(&opts, &0_i32, buf2).unwrap();
+    t2.insert(110, &110).unwrap();
+    let rows: Vec<_> = t2.range(20, 110).unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![
+            (20, Some(25)),
+            (40, Some(45)),
+            (60, Some(65)),
+            (80, Some(85)),
+            (100, Some(105))
+        ]
+    );
+    assert_eq!(t2.get(60).unwrap(), Some(&65));
+    assert_eq!(t2.get(0).unwrap(), Some(&5));
+}
+
+#[test]
+fn finest_archive_is_preferred() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 40)
+        .archive(Consolidation::Min, 0.0, 20, 80)
+        .archive(Consolidation::Max, 0.0, 40, 400);
+    let mut t = Table::new(&opts, &0_u8, buf).unwrap();
+
+    for i in 1..12 {
+        t.insert(i * 10, &(i as u8)).unwrap();
+    }
+
+    assert_eq!(t.get(60).unwrap(), Some(&6));
+    assert_eq!(t.get(45).unwrap(), Some(&4));
+    assert_eq!(t.get(10).unwrap(), Some(&3));
+    let rows: Vec<_> = t.range(50, 110).unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![(40, Some(4)), (60, Some(6)), (80, Some(8)), (100, Some(10))]
+    );
+    let rows: Vec<_> = t.range(0, 110).unwrap().collect();
+    assert_eq!(rows, vec![(0, Some(3)), (40, Some(7)), (80, Some(11))]);
+}
+
+#[test]
+fn xfiles_factor() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 100)
+        .max_fwd_skip(8)
+        .fwd_skip_mode(FwdSkipMode::DoNothing)
+        .archive(Consolidation::Average, 0.5, 40, 400);
+    let mut t = Table::new(&opts, &0.0, buf).unwrap();
+    t.insert(10, &1.0).unwrap();
+    t.insert(20, &2.0).unwrap();
+    t.insert(30, &3.0).unwrap();
+    t.insert(70, &7.0).unwrap();
+    t.insert(80, &8.0).unwrap();
+    t.insert(90, &9.0).unwrap();
+    t.insert(100, &10.0).unwrap();
+    t.insert(110, &11.0).unwrap();
+    t.insert(150, &15.0).unwrap();
+    t.insert(180, &18.0).unwrap();
+    t.insert(190, &19.0).unwrap();
+    let rows: Vec<_> = t.range(0, 190).unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![
+            (0, Some(1.5)),
+            (40, Some(0.0)),
+            (80, Some(9.5)),
+            (120, Some(0.0)),
+            (160, Some(18.5))
+        ]
+    );
+}
+
+#[test]
+fn unknown_rows() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 100)
+        .max_fwd_skip(8)
+        .fwd_skip_mode(FwdSkipMode::Unknown)
+        .archive(Consolidation::Average, 0.5, 40, 400);
+    let mut t = Table::new(&opts, &0.0, buf).unwrap();
+    t.insert(10, &1.0).unwrap();
+    t.insert(70, &7.0).unwrap();
+    t.insert(80, &8.0).unwrap();
+    t.insert(90, &9.0).unwrap();
+    t.insert(100, &10.0).unwrap();
+    t.insert(110, &11.0).unwrap();
+    t.insert(150, &15.0).unwrap();
+    t.insert(180, &18.0).unwrap();
+    t.insert(190, &19.0).unwrap();
+    let rows: Vec<_> = t.range(0, 190).unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![
+            (0, Some(0.5)),
+            (40, None),
+            (80, Some(9.5)),
+            (120, None),
+            (160, Some(18.5))
+        ]
+    );
+}
+
+#[test]
+fn pending() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 40)
+        .pending(Consolidation::Average)
+        .archive(Consolidation::Sum, 0.5, 20, 200);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+    t.insert(10, &4).unwrap();
+    t.insert(15, &8).unwrap();
+    t.insert(20, &1).unwrap();
+    t.insert(30, &2).unwrap();
+    t.insert(40, &0).unwrap();
+    t.insert(50, &5).unwrap();
+    t.insert(60, &6).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&7));
+    assert_eq!(t.get(20).unwrap(), Some(&3));
+    let rows: Vec<_> = t.range(0, 60).unwrap().collect();
+    assert_eq!(rows, vec![(0, Some(7)), (20, Some(3)), (40, Some(5))]);
+}
+
+#[test]
+fn long_gap_fill() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 50)
+        .track_unknown(true)
+        .long_gap_policy(LongGapPolicy::Fill)
+        .archive(Consolidation::Average, 0.5, 20, 100);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+    t.insert(10, &1).unwrap();
+    t.insert(20, &2).unwrap();
+    t.insert(30, &3).unwrap();
+    t.insert(1000, &100).unwrap();
+    assert_eq!(t.first().unwrap(), (960, None));
+    assert_eq!(t.get(990).unwrap(), None);
+    assert_eq!(t.get(1000).unwrap(), Some(&100));
+
+    t.insert(1010, &101).unwrap();
+    let rows: Vec<_> = t.range(920, 1010).unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![
+            (920, None),
+            (940, None),
+            (960, None),
+            (980, None),
+            (1000, Some(100))
+        ]
+    );
+}
+
+#[test]
+fn update() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 40).archive(Consolidation::Sum, 0.5, 20, 200);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+
+    for i in 1..6 {
+        t.insert(i * 10, &(i as i32)).unwrap();
+    }
+
+    t.update_range(20, &[20, 30]).unwrap();
+    t.insert(60, &6).unwrap();
+    t.update_at(60, &60).unwrap();
+    t.insert(70, &7).unwrap();
+    let rows: Vec<_> = t.range(0, 70).unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![(0, Some(1)), (20, Some(50)), (40, Some(9)), (60, Some(67))]
+    );
+}
+
+#[test]
+fn invalid_archives() {
+    let opts = Options::new(0, 10, 100).archive(Consolidation::Last, 0.5, 25, 100);
+    let err = Table::new(&opts, &0_i64, Cursor::new(vec![])).unwrap_err();
+    assert_eq!(err, Error::InvalidArchive);
+
+    let opts = Options::new(0, 10, 100).archive(Consolidation::Sum, 1.0, 20, 100);
+    let err = Table::new(&opts, &0_i64, Cursor::new(vec![])).unwrap_err();
+    assert_eq!(err, Error::InvalidArchive);
+
+    let opts = (0..9).fold(Options::new(0, 10, 100), |o, _| {
+        o.archive(Consolidation::Last, 0.5, 20, 100)
+    });
+    let err = Table::new(&opts, &0_i64, Cursor::new(vec![])).unwrap_err();
+    assert_eq!(err, Error::InvalidArchive);
+}
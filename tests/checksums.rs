@@ -0,0 +1,101 @@
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::rtdb::Table;
+use std::io::Cursor;
+
+fn table(opts: &Options) -> Vec<u8> {
+    let mut t = Table::new(opts, &0_u32, Cursor::new(vec![])).unwrap();
+
+    for i in 1..4 {
+        t.insert(i * 10, &(i as u32)).unwrap();
+    }
+
+    t.into_inner().into_inner()
+}
+
+#[test]
+fn slots() {
+    let opts = Options::new(0, 10, 40).checksums(true);
+    let mut buf = table(&opts);
+    let len = buf.len() as u64;
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(buf.clone())).unwrap();
+    assert_eq!(t.verify(), Ok(()));
+    assert_eq!(t.get(30).unwrap(), Some(&3));
+
+    buf[len as usize - 6] ^= 1;
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(buf)).unwrap();
+    assert_eq!(t.get(20).unwrap(), Some(&2));
+    assert_eq!(t.get(30), Err(Error::ChecksumMismatch(len - 8)));
+    assert_eq!(t.verify(), Err(Error::ChecksumMismatch(len - 8)));
+
+    let mut iter = t.iter().unwrap();
+    let rows: Vec<_> = iter.by_ref().collect();
+    assert_eq!(rows, vec![(0, Some(0)), (10, Some(1)), (20, Some(2))]);
+    assert_eq!(iter.error(), Some(&Error::ChecksumMismatch(len - 8)));
+}
+
+#[test]
+fn queries() {
+    let opts = Options::new(0, 10, 40).checksums(true);
+    let mut buf = table(&opts);
+    let len = buf.len() as u64;
+    buf[len as usize - 6] ^= 1;
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(buf)).unwrap();
+    let err = Error::ChecksumMismatch(len - 8);
+    assert_eq!(t.aggregate(0, 30).err(), Some(err));
+    let err = Error::ChecksumMismatch(len - 8);
+    assert_eq!(t.quantile(0, 30, 0.5), Err(err));
+    assert_eq!(t.aggregate(0, 20).unwrap().sum(), 3);
+    assert_eq!(t.quantile(0, 20, 1.0), Ok(2));
+}
+
+#[test]
+fn zeroed_slots() {
+    let opts = Options::new(0, 10, 40).checksums(true);
+    let mut buf = table(&opts);
+    let len = buf.len();
+    buf[len - 8..].fill(0);
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(buf)).unwrap();
+    assert_eq!(t.get(30), Err(Error::ChecksumMismatch(len as u64 - 8)));
+    assert_eq!(t.verify(), Err(Error::ChecksumMismatch(len as u64 - 8)));
+
+    let opts = opts
+        .fwd_skip_mode(FwdSkipMode::DoNothing)
+        .track_unknown(true);
+    let mut t = Table::new(&opts, &0_u32, Cursor::new(vec![])).unwrap();
+    t.insert(30, &3).unwrap();
+    assert_eq!(t.verify(), Ok(()));
+    assert_eq!(t.get(10).unwrap(), None);
+}
+
+#[test]
+fn header() {
+    let opts = Options::new(0, 10, 40).checksums(true);
+    let mut buf = table(&opts);
+    buf[30] ^= 1;
+    let res = Table::load(&opts, &0_u32, Cursor::new(buf));
+    assert_eq!(res.err(), Some(Error::ChecksumMismatch(0)));
+}
+
+#[test]
+fn archives() {
+    let opts = Options::new(0, 10, 100)
+        .checksums(true)
+        .stamp_slots(true)
+        .fwd_skip_mode(FwdSkipMode::DoNothing)
+        .max_fwd_skip(5)
+        .archive(Consolidation::Max, 0.5, 20, 200);
+    let mut t = Table::new(&opts, &0_u32, Cursor::new(vec![])).unwrap();
+    t.insert(10, &1).unwrap();
+    t.insert(60, &6).unwrap();
+    assert_eq!(t.verify(), Ok(()));
+    assert_eq!(t.get(30), Err(Error::StaleSlot));
+
+    let mut t = Table::load(&opts, &0_u32, t.into_inner()).unwrap();
+    t.insert(110, &11).unwrap();
+    t.insert(160, &16).unwrap();
+    assert_eq!(t.verify(), Ok(()));
+    let rows: Vec<_> = t.range(0, 160).unwrap().collect();
+    let known: Vec<_> = rows.into_iter().filter(|(_, v)| v != &Some(0)).collect();
+    assert_eq!(known, vec![(0, Some(1)), (60, Some(6)), (100, Some(11))]);
+}
@@ -32,14 +32,14 @@ fn partial_load() {
     t.insert(100, &1000).unwrap();
     t.insert(200, &2000).unwrap();
     t.insert(300, &3000).unwrap();
-    assert_eq!(t.get(100).unwrap(), &1000);
-    assert_eq!(t.get(200).unwrap(), &2000);
-    assert_eq!(t.get(300).unwrap(), &3000);
+    assert_eq!(t.get(100).unwrap(), Some(&1000));
+    assert_eq!(t.get(200).unwrap(), Some(&2000));
+    assert_eq!(t.get(300).unwrap(), Some(&3000));
     let buf2 = t.into_inner();
     let mut t2 = Table::load(&opts, &0_i16, buf2).unwrap();
-    assert_eq!(t2.get(100).unwrap(), &1000);
-    assert_eq!(t2.get(200).unwrap(), &2000);
-    assert_eq!(t2.get(300).unwrap(), &3000);
+    assert_eq!(t2.get(100).unwrap(), Some(&1000));
+    assert_eq!(t2.get(200).unwrap(), Some(&2000));
+    assert_eq!(t2.get(300).unwrap(), Some(&3000));
     let mut v2 = t2.into_inner().into_inner();
     v2.pop().unwrap();
     let buf3 = Cursor::new(v2);
@@ -50,6 +50,7 @@ fn partial_load() {
 }
 
 #[test]
+#[allow(clippy::slow_vector_initialization)]
 fn full_load() {
     let mut v: Vec<u8> = vec![];
     v.resize(40, 0_u8);
@@ -66,14 +67,14 @@ fn full_load() {
     t.insert(800, &80000).unwrap();
     t.insert(900, &90000).unwrap();
     t.insert(1000, &100000).unwrap();
-    assert_eq!(t.get(1000).unwrap(), &100000);
-    assert_eq!(t.get(200).unwrap(), &20000);
-    assert_eq!(t.get(300).unwrap(), &30000);
+    assert_eq!(t.get(1000).unwrap(), Some(&100000));
+    assert_eq!(t.get(200).unwrap(), Some(&20000));
+    assert_eq!(t.get(300).unwrap(), Some(&30000));
     let buf2 = t.into_inner();
     let mut t2 = Table::load(&opts, &0_i32, buf2).unwrap();
-    assert_eq!(t2.get(1000).unwrap(), &100000);
-    assert_eq!(t2.get(200).unwrap(), &20000);
-    assert_eq!(t2.get(300).unwrap(), &30000);
+    assert_eq!(t2.get(1000).unwrap(), Some(&100000));
+    assert_eq!(t2.get(200).unwrap(), Some(&20000));
+    assert_eq!(t2.get(300).unwrap(), Some(&30000));
     let mut v2 = t2.into_inner().into_inner();
     v2.pop().unwrap();
     let buf3 = Cursor::new(v2);
@@ -83,6 +84,36 @@ fn full_load() {
     );
 }
 
+#[test]
+fn preallocate() {
+    let opts = Options::new(0, 10, 100)
+        .journal(true)
+        .checksums(true)
+        .stamp_slots(true)
+        .track_unknown(true)
+        .pending(Consolidation::Max);
+
+    for opts in [opts, opts.archive(Consolidation::Average, 0.5, 20, 400)] {
+        let t = roundtable::create::in_memory(opts.preallocate(true), 0_u64).unwrap();
+        let buf = t.into_inner().into_inner();
+        let len = buf.len();
+        let mut t = Table::load(&opts, &0_u64, Cursor::new(buf)).unwrap();
+        assert_eq!(t.last().unwrap(), (0, Some(&0)));
+
+        let mut t2 = roundtable::create::in_memory(opts, 0_u64).unwrap();
+
+        for i in 1..20 {
+            t.insert(i * 10, &i).unwrap();
+            t2.insert(i * 10, &i).unwrap();
+        }
+
+        let full = t2.into_inner().into_inner();
+        assert_eq!(full.len(), len);
+        assert_eq!(t.into_inner().into_inner(), full);
+        Table::load(&opts, &0_u64, Cursor::new(full)).unwrap();
+    }
+}
+
 #[test]
 fn time_errors() {
     let t_start = 1000;
@@ -105,8 +136,8 @@ fn time_errors() {
     assert_eq!(t.get(999).unwrap_err(), Error::OutOfRangePast);
     assert_eq!(t.get(1100).unwrap_err(), Error::OutOfRangePast);
     assert_eq!(t.get(1199).unwrap_err(), Error::OutOfRangePast);
-    assert_eq!(t.get(1200).unwrap(), &200);
-    assert_eq!(t.get(2100).unwrap(), &210);
+    assert_eq!(t.get(1200).unwrap(), Some(&200));
+    assert_eq!(t.get(2100).unwrap(), Some(&210));
     assert_eq!(t.get(2101).unwrap_err(), Error::OutOfRangeFuture);
 }
 
@@ -123,20 +154,20 @@ fn boundary_values() {
     t.insert(150, &1).unwrap();
     t.insert(250, &2).unwrap();
     t.insert(350, &3).unwrap();
-    assert_eq!(t.get(0).unwrap(), &0);
-    assert_eq!(t.get(99).unwrap(), &0);
-    assert_eq!(t.get(100).unwrap(), &1);
-    assert_eq!(t.get(199).unwrap(), &1);
-    assert_eq!(t.get(200).unwrap(), &2);
-    assert_eq!(t.get(299).unwrap(), &2);
-    assert_eq!(t.get(300).unwrap(), &3);
+    assert_eq!(t.get(0).unwrap(), Some(&0));
+    assert_eq!(t.get(99).unwrap(), Some(&0));
+    assert_eq!(t.get(100).unwrap(), Some(&1));
+    assert_eq!(t.get(199).unwrap(), Some(&1));
+    assert_eq!(t.get(200).unwrap(), Some(&2));
+    assert_eq!(t.get(299).unwrap(), Some(&2));
+    assert_eq!(t.get(300).unwrap(), Some(&3));
     t.insert(950, &9).unwrap();
     t.insert(1050, &10).unwrap();
     t.insert(1100, &11).unwrap();
-    assert_eq!(t.get(900).unwrap(), &9);
-    assert_eq!(t.get(999).unwrap(), &9);
-    assert_eq!(t.get(1000).unwrap(), &10);
-    assert_eq!(t.get(1099).unwrap(), &10);
+    assert_eq!(t.get(900).unwrap(), Some(&9));
+    assert_eq!(t.get(999).unwrap(), Some(&9));
+    assert_eq!(t.get(1000).unwrap(), Some(&10));
+    assert_eq!(t.get(1099).unwrap(), Some(&10));
 }
 
 #[test]
@@ -154,21 +185,242 @@ fn zeroed() {
     t.insert(150, &5).unwrap();
     t.insert(190, &9).unwrap();
     t.insert(240, &14).unwrap();
-    assert_eq!(t.get(100).unwrap(), &0);
-    assert_eq!(t.get(110).unwrap(), &1);
-    assert_eq!(t.get(120).unwrap(), &2);
-    assert_eq!(t.get(130).unwrap(), &0);
-    assert_eq!(t.get(140).unwrap(), &0);
-    assert_eq!(t.get(150).unwrap(), &5);
-    assert_eq!(t.get(160).unwrap(), &0);
-    assert_eq!(t.get(170).unwrap(), &0);
-    assert_eq!(t.get(180).unwrap(), &0);
-    assert_eq!(t.get(190).unwrap(), &9);
-    assert_eq!(t.get(200).unwrap(), &0);
-    assert_eq!(t.get(210).unwrap(), &0);
-    assert_eq!(t.get(220).unwrap(), &0);
-    assert_eq!(t.get(230).unwrap(), &0);
-    assert_eq!(t.get(240).unwrap(), &14);
+    assert_eq!(t.get(100).unwrap(), Some(&0));
+    assert_eq!(t.get(110).unwrap(), Some(&1));
+    assert_eq!(t.get(120).unwrap(), Some(&2));
+    assert_eq!(t.get(130).unwrap(), Some(&0));
+    assert_eq!(t.get(140).unwrap(), Some(&0));
+    assert_eq!(t.get(150).unwrap(), Some(&5));
+    assert_eq!(t.get(160).unwrap(), Some(&0));
+    assert_eq!(t.get(170).unwrap(), Some(&0));
+    assert_eq!(t.get(180).unwrap(), Some(&0));
+    assert_eq!(t.get(190).unwrap(), Some(&9));
+    assert_eq!(t.get(200).unwrap(), Some(&0));
+    assert_eq!(t.get(210).unwrap(), Some(&0));
+    assert_eq!(t.get(220).unwrap(), Some(&0));
+    assert_eq!(t.get(230).unwrap(), Some(&0));
+    assert_eq!(t.get(240).unwrap(), Some(&14));
+}
+
+#[test]
+fn unknown() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(100, 10, 500)
+        .max_fwd_skip(8)
+        .fwd_skip_mode(FwdSkipMode::Unknown);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+    t.insert(110, &1).unwrap();
+    t.insert(120, &0).unwrap();
+    t.insert(150, &5).unwrap();
+    assert_eq!(t.get(100).unwrap(), Some(&0));
+    assert_eq!(t.get(120).unwrap(), Some(&0));
+    assert_eq!(t.get(130).unwrap(), None);
+    assert_eq!(t.get(140).unwrap(), None);
+    assert_eq!(t.get(150).unwrap(), Some(&5));
+    let rows: Vec<_> = t.range(110, 150).unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![
+            (110, Some(1)),
+            (120, Some(0)),
+            (130, None),
+            (140, None),
+            (150, Some(5))
+        ]
+    );
+    assert_eq!(t.aggregate(110, 150).unwrap().count(), 3);
+
+    let buf2 = t.into_inner();
+    let mut t2 = Table::load(&opts, &0_i32, buf2).unwrap();
+    assert_eq!(t2.get(140).unwrap(), None);
+    assert_eq!(t2.last().unwrap(), (150, Some(&5)));
+
+    let opts = Options::new(100, 10, 500).fwd_skip_mode(FwdSkipMode::Unknown);
+    let buf = Table::new(&Options::new(100, 10, 500), &0_i32, Cursor::new(vec![]))
+        .unwrap()
+        .into_inner();
+    let err = Table::load(&opts, &0_i32, buf).unwrap_err();
+    assert_eq!(err, Error::InvalidSkipMode);
+}
+
+#[test]
+fn stale_slots() {
+    let opts = Options::new(0, 10, 50)
+        .max_fwd_skip(3)
+        .fwd_skip_mode(FwdSkipMode::DoNothing);
+
+    for stamp in [false, true] {
+        let buf = Cursor::new(vec![]);
+        let opts = opts.stamp_slots(stamp);
+        let mut t = Table::new(&opts, &0_u32, buf).unwrap();
+
+        for i in 1..6 {
+            t.insert(i * 10, &(i as u32)).unwrap();
+        }
+
+        t.insert(90, &9).unwrap();
+        assert_eq!(t.get(50).unwrap(), Some(&5));
+        assert_eq!(t.get(90).unwrap(), Some(&9));
+
+        if stamp {
+            assert_eq!(t.get(60).unwrap_err(), Error::StaleSlot);
+            assert_eq!(t.get(80).unwrap_err(), Error::StaleSlot);
+            let rows: Vec<_> = t.iter().unwrap().collect();
+            assert_eq!(
+                rows,
+                vec![
+                    (50, Some(5)),
+                    (60, None),
+                    (70, None),
+                    (80, None),
+                    (90, Some(9))
+                ]
+            );
+        } else {
+            assert_eq!(t.get(60).unwrap(), Some(&1));
+            assert_eq!(t.get(80).unwrap(), Some(&3));
+        }
+    }
+}
+
+#[test]
+fn pending() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 100).pending(Consolidation::Average);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+    t.insert(12, &2).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&2));
+    t.insert(15, &5).unwrap();
+    t.insert(19, &9).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&6));
+    assert_eq!(t.insert(19, &9).unwrap_err(), Error::UpdateTooEarly);
+    t.insert(25, &20).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&6));
+    assert_eq!(t.get(20).unwrap(), Some(&20));
+
+    let buf2 = t.into_inner();
+    let mut t2 = Table::load(&opts, &0_i32, buf2).unwrap();
+    t2.insert(27, &34).unwrap();
+    assert_eq!(t2.last().unwrap(), (27, Some(&24)));
+
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 100).pending(Consolidation::Max);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+    t.insert(11, &5).unwrap();
+    t.insert(13, &9).unwrap();
+    t.insert(16, &1).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&9));
+    t.update_at(10, &3).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&3));
+    t.insert(18, &4).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&4));
+}
+
+#[test]
+fn heartbeat() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 200)
+        .heartbeat(35)
+        .fwd_skip_mode(FwdSkipMode::Linear);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+    t.insert(10, &1).unwrap();
+    t.insert(40, &4).unwrap();
+    t.insert(100, &10).unwrap();
+    assert_eq!(t.get(20).unwrap(), Some(&2));
+    assert_eq!(t.get(30).unwrap(), Some(&3));
+    assert_eq!(t.get(50).unwrap(), None);
+    assert_eq!(t.get(90).unwrap(), None);
+    assert_eq!(t.get(100).unwrap(), Some(&10));
+
+    let buf2 = t.into_inner();
+    let opts2 = Options::new(0, 10, 200);
+    let mut t2 = Table::load(&opts2, &0_i32, buf2).unwrap();
+    t2.insert(150, &15).unwrap();
+    assert_eq!(t2.get(140).unwrap(), None);
+    assert_eq!(t2.get(150).unwrap(), Some(&15));
+
+    let opts = Options::new(0, 10, 200).heartbeat(5);
+    let err = Table::new(&opts, &0_i32, Cursor::new(vec![])).unwrap_err();
+    assert_eq!(err, Error::InvalidHeartbeat);
+}
+
+#[test]
+fn long_gap_reset() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 50).long_gap_policy(LongGapPolicy::Reset);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+
+    for i in 1..5 {
+        t.insert(i * 10, &(i as i32)).unwrap();
+    }
+
+    t.insert(1005, &100).unwrap();
+    assert_eq!(t.first().unwrap(), (1000, Some(&100)));
+    assert_eq!(t.get(990).unwrap_err(), Error::OutOfRangePast);
+
+    let buf2 = t.into_inner();
+    let mut t2 = Table::load(&Options::new(0, 10, 50), &0_i32, buf2).unwrap();
+    t2.insert(1010, &101).unwrap();
+    let rows: Vec<_> = t2.iter().unwrap().collect();
+    assert_eq!(rows, vec![(1000, Some(100)), (1010, Some(101))]);
+}
+
+#[test]
+fn update() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 40);
+    let mut t = Table::new(&opts, &0_i32, buf).unwrap();
+
+    for i in 1..6 {
+        t.insert(i * 10, &(i as i32)).unwrap();
+    }
+
+    t.update_at(25, &20).unwrap();
+    t.update_range(30, &[30, 40]).unwrap();
+    let rows: Vec<_> = t.iter().unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![
+            (20, Some(20)),
+            (30, Some(30)),
+            (40, Some(40)),
+            (50, Some(5))
+        ]
+    );
+    assert_eq!(t.update_at(10, &1).unwrap_err(), Error::OutOfRangePast);
+    assert_eq!(t.update_at(60, &1).unwrap_err(), Error::OutOfRangeFuture);
+    let err = t.update_range(40, &[1, 2, 3]).unwrap_err();
+    assert_eq!(err, Error::OutOfRangeFuture);
+    assert_eq!(t.get(40).unwrap(), Some(&40));
+}
+
+#[test]
+fn insert_many() {
+    let opts = Options::new(0, 10, 60)
+        .stamp_slots(true)
+        .archive(Consolidation::Max, 0.5, 20, 100);
+    let items: Vec<_> = (1..20).map(|i| (i * 10, i as i64 * 3)).collect();
+    let mut t1 = Table::new(&opts, &0_i64, Cursor::new(vec![])).unwrap();
+    let mut t2 = Table::new(&opts, &0_i64, Cursor::new(vec![])).unwrap();
+
+    for (t, v) in items.iter() {
+        t1.insert(*t, v).unwrap();
+    }
+
+    t2.insert_many(items).unwrap();
+    assert_eq!(t2.last().unwrap(), (190, Some(&57)));
+    assert_eq!(t1.into_inner().into_inner(), t2.into_inner().into_inner());
+
+    let mut t = Table::new(&opts, &0_i64, Cursor::new(vec![])).unwrap();
+    let err = t.insert_many([(10, 1), (20, 2), (20, 3)]).unwrap_err();
+    assert_eq!(err, Error::InsertFailed(2, Box::new(Error::UpdateTooEarly)));
+    assert_eq!(t.last().unwrap(), (0, Some(&0)));
+    let err = t.insert_many([(10, 1), (20, 2), (60, 6)]).unwrap_err();
+    assert_eq!(
+        err,
+        Error::InsertFailed(2, Box::new(Error::MaxSkipExceeded))
+    );
+    assert_eq!(t.last().unwrap(), (20, Some(&2)));
 }
 
 #[test]
@@ -186,20 +438,20 @@ fn nearest() {
     t.insert(60, &6).unwrap();
     t.insert(100, &10).unwrap();
     t.insert(140, &14).unwrap();
-    assert_eq!(t.get(10).unwrap(), &1);
-    assert_eq!(t.get(20).unwrap(), &3);
-    assert_eq!(t.get(30).unwrap(), &3);
-    assert_eq!(t.get(40).unwrap(), &3);
-    assert_eq!(t.get(50).unwrap(), &6);
-    assert_eq!(t.get(60).unwrap(), &6);
-    assert_eq!(t.get(70).unwrap(), &6);
-    assert_eq!(t.get(80).unwrap(), &10);
-    assert_eq!(t.get(90).unwrap(), &10);
-    assert_eq!(t.get(100).unwrap(), &10);
-    assert_eq!(t.get(110).unwrap(), &10);
-    assert_eq!(t.get(120).unwrap(), &14);
-    assert_eq!(t.get(130).unwrap(), &14);
-    assert_eq!(t.get(140).unwrap(), &14);
+    assert_eq!(t.get(10).unwrap(), Some(&1));
+    assert_eq!(t.get(20).unwrap(), Some(&3));
+    assert_eq!(t.get(30).unwrap(), Some(&3));
+    assert_eq!(t.get(40).unwrap(), Some(&3));
+    assert_eq!(t.get(50).unwrap(), Some(&6));
+    assert_eq!(t.get(60).unwrap(), Some(&6));
+    assert_eq!(t.get(70).unwrap(), Some(&6));
+    assert_eq!(t.get(80).unwrap(), Some(&10));
+    assert_eq!(t.get(90).unwrap(), Some(&10));
+    assert_eq!(t.get(100).unwrap(), Some(&10));
+    assert_eq!(t.get(110).unwrap(), Some(&10));
+    assert_eq!(t.get(120).unwrap(), Some(&14));
+    assert_eq!(t.get(130).unwrap(), Some(&14));
+    assert_eq!(t.get(140).unwrap(), Some(&14));
 }
 
 #[test]
@@ -215,14 +467,14 @@ fn lerp_int() {
     t.insert(10, &10).unwrap();
     t.insert(40, &40).unwrap();
     t.insert(80, &60).unwrap();
-    assert_eq!(t.get(10).unwrap(), &10);
-    assert_eq!(t.get(20).unwrap(), &20);
-    assert_eq!(t.get(30).unwrap(), &30);
-    assert_eq!(t.get(40).unwrap(), &40);
-    assert_eq!(t.get(50).unwrap(), &45);
-    assert_eq!(t.get(60).unwrap(), &50);
-    assert_eq!(t.get(70).unwrap(), &55);
-    assert_eq!(t.get(80).unwrap(), &60);
+    assert_eq!(t.get(10).unwrap(), Some(&10));
+    assert_eq!(t.get(20).unwrap(), Some(&20));
+    assert_eq!(t.get(30).unwrap(), Some(&30));
+    assert_eq!(t.get(40).unwrap(), Some(&40));
+    assert_eq!(t.get(50).unwrap(), Some(&45));
+    assert_eq!(t.get(60).unwrap(), Some(&50));
+    assert_eq!(t.get(70).unwrap(), Some(&55));
+    assert_eq!(t.get(80).unwrap(), Some(&60));
 }
 
 #[test]
@@ -237,15 +489,15 @@ fn lerp_float() {
     let mut t = Table::new(&opts, &0.0, buf).unwrap();
     t.insert(40, &1.0).unwrap();
     t.insert(80, &3.0).unwrap();
-    assert_eq!(t.get(0).unwrap(), &0.0);
-    assert_eq!(t.get(10).unwrap(), &0.25);
-    assert_eq!(t.get(20).unwrap(), &0.50);
-    assert_eq!(t.get(30).unwrap(), &0.75);
-    assert_eq!(t.get(40).unwrap(), &1.0);
-    assert_eq!(t.get(50).unwrap(), &1.5);
-    assert_eq!(t.get(60).unwrap(), &2.0);
-    assert_eq!(t.get(70).unwrap(), &2.5);
-    assert_eq!(t.get(80).unwrap(), &3.0);
+    assert_eq!(t.get(0).unwrap(), Some(&0.0));
+    assert_eq!(t.get(10).unwrap(), Some(&0.25));
+    assert_eq!(t.get(20).unwrap(), Some(&0.50));
+    assert_eq!(t.get(30).unwrap(), Some(&0.75));
+    assert_eq!(t.get(40).unwrap(), Some(&1.0));
+    assert_eq!(t.get(50).unwrap(), Some(&1.5));
+    assert_eq!(t.get(60).unwrap(), Some(&2.0));
+    assert_eq!(t.get(70).unwrap(), Some(&2.5));
+    assert_eq!(t.get(80).unwrap(), Some(&3.0));
 }
 
 #[test]
@@ -259,11 +511,11 @@ fn lerp_array() {
         .max_fwd_skip(8);
     let mut t = Table::new(&opts, &[1.0, 2.0, 3.0], buf).unwrap();
     t.insert(40, &[3.0, 4.0, 6.0]).unwrap();
-    assert_eq!(t.get(0).unwrap(), &[1.0, 2.0, 3.0]);
-    assert_eq!(t.get(10).unwrap(), &[1.5, 2.5, 3.75]);
-    assert_eq!(t.get(20).unwrap(), &[2.0, 3.0, 4.5]);
-    assert_eq!(t.get(30).unwrap(), &[2.5, 3.5, 5.25]);
-    assert_eq!(t.get(40).unwrap(), &[3.0, 4.0, 6.0]);
+    assert_eq!(t.get(0).unwrap(), Some(&[1.0, 2.0, 3.0]));
+    assert_eq!(t.get(10).unwrap(), Some(&[1.5, 2.5, 3.75]));
+    assert_eq!(t.get(20).unwrap(), Some(&[2.0, 3.0, 4.5]));
+    assert_eq!(t.get(30).unwrap(), Some(&[2.5, 3.5, 5.25]));
+    assert_eq!(t.get(40).unwrap(), Some(&[3.0, 4.0, 6.0]));
 }
 
 #[test]
@@ -292,38 +544,38 @@ fn lerp_struct() {
     .unwrap();
     assert_eq!(
         t.get(0).unwrap(),
-        &Foo {
+        Some(&Foo {
             a: 0,
             b: [0.0, 0.0]
-        }
+        })
     );
     assert_eq!(
         t.get(10).unwrap(),
-        &Foo {
+        Some(&Foo {
             a: 1,
             b: [0.5, 0.75]
-        }
+        })
     );
     assert_eq!(
         t.get(20).unwrap(),
-        &Foo {
+        Some(&Foo {
             a: 2,
             b: [1.0, 1.5]
-        }
+        })
     );
     assert_eq!(
         t.get(30).unwrap(),
-        &Foo {
+        Some(&Foo {
             a: 3,
             b: [1.5, 2.25]
-        }
+        })
     );
     assert_eq!(
         t.get(40).unwrap(),
-        &Foo {
+        Some(&Foo {
             a: 4,
             b: [2.0, 3.0]
-        }
+        })
     );
 }
 
@@ -340,24 +592,25 @@ fn first_and_last() {
     t.insert(t_start + 193, &1).unwrap();
     t.insert(t_start + 386, &2).unwrap();
     t.insert(t_start + 579, &3).unwrap();
-    assert_eq!(t.first().unwrap(), (t_start, &0));
-    assert_eq!(t.last().unwrap(), (t_start + 579, &3));
+    assert_eq!(t.first().unwrap(), (t_start, Some(&0)));
+    assert_eq!(t.last().unwrap(), (t_start + 579, Some(&3)));
     t.insert(t_start + 1678, &8).unwrap();
     t.insert(t_start + 1737, &9).unwrap();
     t.insert(t_start + 1930, &10).unwrap();
-    assert_eq!(t.first().unwrap(), (t_start + 193, &1));
-    assert_eq!(t.last().unwrap(), (t_start + 1930, &10));
-    assert_eq!(t.get(t_start + 195).unwrap(), &1);
-    assert_eq!(t.get(t_start + 579).unwrap(), &3);
-    assert_eq!(t.get(t_start + 1678).unwrap(), &8);
-    assert_eq!(t.get(t_start + 1737).unwrap(), &9);
-    assert_eq!(t.get(t_start + 1930).unwrap(), &10);
+    assert_eq!(t.first().unwrap(), (t_start + 193, Some(&1)));
+    assert_eq!(t.last().unwrap(), (t_start + 1930, Some(&10)));
+    assert_eq!(t.get(t_start + 195).unwrap(), Some(&1));
+    assert_eq!(t.get(t_start + 579).unwrap(), Some(&3));
+    assert_eq!(t.get(t_start + 1678).unwrap(), Some(&8));
+    assert_eq!(t.get(t_start + 1737).unwrap(), Some(&9));
+    assert_eq!(t.get(t_start + 1930).unwrap(), Some(&10));
     t.insert(t_start + 2123, &11).unwrap();
-    assert_eq!(t.first().unwrap(), (t_start + 386, &2));
-    assert_eq!(t.last().unwrap(), (t_start + 2123, &11));
+    assert_eq!(t.first().unwrap(), (t_start + 386, Some(&2)));
+    assert_eq!(t.last().unwrap(), (t_start + 2123, Some(&11)));
 }
 
 #[test]
+#[allow(clippy::explicit_counter_loop)]
 fn iter() {
     let t_start = 1665232907;
     let t_step = 119;
@@ -374,12 +627,13 @@ fn iter() {
 
     for (t, v) in tab.iter().unwrap() {
         assert_eq!(t, t_start + t_step * j);
-        assert_eq!(v, j);
+        assert_eq!(v, Some(j));
         j += 1;
     }
 }
 
 #[test]
+#[allow(clippy::explicit_counter_loop)]
 fn range() {
     let t_start = 1665232907;
     let t_step = 119;
@@ -396,7 +650,7 @@ fn range() {
 
     for (t, v) in tab.range(1665235287, 1665239333).unwrap() {
         assert_eq!(t, t_start + t_step * j);
-        assert_eq!(v, j);
+        assert_eq!(v, Some(j));
         j += 1;
     }
 }
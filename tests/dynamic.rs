@@ -0,0 +1,251 @@
+use roundtable as rt;
+use roundtable::data::Discrete;
+use roundtable::dynamic::{DynTable, Record, Value};
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::schema::{Primitive, Schema};
+use std::io::Cursor;
+
+roundtable::datapoint! {
+    enum State {
+        Up,
+        Down,
+    }
+
+    struct Sample {
+        load: [f32; 2],
+        rx: u64,
+        up: bool,
+        state: State,
+        errs: Option<u16>,
+    }
+
+    struct Link {
+        rx: u64,
+        tx: u64,
+    }
+}
+
+fn sample(i: u64) -> Sample {
+    Sample {
+        load: [i as f32 / 2.0, i as f32],
+        rx: i * 100,
+        up: !i.is_multiple_of(3),
+        state: if i.is_multiple_of(4) {
+            State::Down
+        } else {
+            State::Up
+        },
+        errs: i.is_multiple_of(2).then_some(i as u16),
+    }
+}
+
+fn record(schema: &Schema, s: &Sample) -> Record {
+    let values = vec![
+        s.load[0].into(),
+        s.load[1].into(),
+        s.rx.into(),
+        s.up.into(),
+        Value::U32(s.state.to_index()),
+        s.errs.is_some().into(),
+        s.errs.unwrap_or(0).into(),
+    ];
+    Record::from_values(schema, values).unwrap()
+}
+
+#[test]
+fn same_bytes_as_typed() {
+    let opts = Options::new(0, 10, 100)
+        .track_unknown(true)
+        .pending(Consolidation::Average)
+        .archive(Consolidation::Max, 0.5, 20, 200);
+    let schema = Sample::default().get_schema();
+    let mut typed = rt::create::in_memory(opts, sample(0)).unwrap();
+    let first = record(&schema, &sample(0));
+    let mut dynamic = DynTable::new(&opts, &first, Cursor::new(vec![])).unwrap();
+
+    for (t, i) in [
+        (10, 1),
+        (14, 2),
+        (20, 3),
+        (30, 4),
+        (36, 5),
+        (60, 6),
+        (70, 7),
+    ] {
+        typed.insert(t, &sample(i)).unwrap();
+        dynamic.insert(t, &record(&schema, &sample(i))).unwrap();
+    }
+
+    let rows: Vec<_> = typed
+        .iter()
+        .unwrap()
+        .map(|(t, v)| (t, v.map(|v| record(&schema, &v))))
+        .collect();
+    assert_eq!(dynamic.iter().unwrap().collect::<Vec<_>>(), rows);
+
+    let typed_max = record(&schema, &typed.aggregate(0, 70).unwrap().max());
+    let typed_median = record(&schema, &typed.quantile(0, 70, 0.5).unwrap());
+    assert_eq!(dynamic.aggregate(0, 70).unwrap().max(), typed_max);
+    assert_eq!(dynamic.quantile(0, 70, 0.5).unwrap(), typed_median);
+
+    let typed = typed.into_inner().into_inner();
+    assert_eq!(dynamic.into_inner().into_inner(), typed);
+
+    let mut t = DynTable::load(&opts, Cursor::new(typed)).unwrap();
+    assert_eq!(t.schema(), &schema);
+    assert_eq!(t.last().unwrap(), (70, Some(&record(&schema, &sample(7)))));
+    assert_eq!(t.iter().unwrap().collect::<Vec<_>>(), rows);
+}
+
+#[test]
+fn typed_load_of_dynamic_table() {
+    let schema = Schema::Struct(
+        "Link".to_string(),
+        vec![
+            ("rx".to_string(), Schema::Primitive(Primitive::U64)),
+            ("tx".to_string(), Schema::Primitive(Primitive::U64)),
+        ],
+    );
+    let opts = Options::new(0, 10, 100).source(SourceKind::Counter);
+    let mut t = DynTable::new(&opts, &Record::new(&schema), Cursor::new(vec![])).unwrap();
+
+    for (time, rx, tx) in [(10, 100, 50), (20, 300, 60)] {
+        let mut dp = t.record();
+        dp.set("rx", Value::U64(rx)).unwrap();
+        dp.set("tx", Value::U64(tx)).unwrap();
+        t.insert(time, &dp).unwrap();
+    }
+
+    let rate = t.get(20).unwrap().unwrap();
+    assert_eq!(rate.get("rx"), Some(Value::U64(20)));
+    assert_eq!(rate.get("tx"), Some(Value::U64(1)));
+
+    let buf = t.into_inner().into_inner();
+    let mut t = rt::load::from_buffer::<Link, _>(opts, buf).unwrap();
+    assert_eq!(t.get(20).unwrap(), Some(&Link { rx: 20, tx: 1 }));
+}
+
+#[test]
+fn records() {
+    let schema = Sample::default().get_schema();
+    let mut dp = Record::new(&schema);
+    assert_eq!(dp.get_size(), Sample::default().get_size());
+    assert_eq!(dp.get_hash(), Sample::default().get_hash());
+    assert_eq!(dp.get("load[1]"), Some(Value::F32(0.0)));
+    assert_eq!(dp.get("errs.some"), Some(Value::Bool(false)));
+    assert_eq!(dp.get("errs"), None);
+
+    dp.set("errs.value", Value::U16(3)).unwrap();
+    assert_eq!(dp.get("errs.value"), Some(Value::U16(3)));
+    assert_eq!(
+        dp.set("rx", Value::U32(1)).err(),
+        Some(Error::SchemaMismatch(
+            "`rx` is u64 but the value is u32".to_string()
+        ))
+    );
+    assert_eq!(
+        dp.set("state", Value::U32(2)).err(),
+        Some(Error::SchemaMismatch(
+            "`state` has no variant 2".to_string()
+        ))
+    );
+    assert_eq!(
+        dp.set("tx", Value::U64(1)).err(),
+        Some(Error::SchemaMismatch("`tx` is not stored".to_string()))
+    );
+    assert_eq!(
+        Record::from_values(&schema, vec![]).err(),
+        Some(Error::SchemaMismatch(
+            "schema has 7 values but 0 were given".to_string()
+        ))
+    );
+}
+
+#[test]
+fn schema_errors() {
+    let opts = Options::new(0, 10, 100);
+    let t = rt::create::in_memory(opts, sample(0)).unwrap();
+    let mut t = DynTable::load(&opts, t.into_inner()).unwrap();
+    let link = Record::new(&Link::default().get_schema());
+    let res = t.insert(10, &link);
+    let diff = "`load[0]` is stored as f32 but `rx` is u64";
+    assert_eq!(res.err(), Some(Error::SchemaMismatch(diff.to_string())));
+
+    let res = t.insert_many(vec![(10, t.record()), (20, link)]);
+    let diff = Error::SchemaMismatch(diff.to_string());
+    assert_eq!(res.err(), Some(Error::InsertFailed(1, Box::new(diff))));
+
+    let mut legacy = vec![];
+    legacy.extend_from_slice(&0x42445452_u32.to_le_bytes());
+
+    for v in [4, 0_u32.get_hash(), 4, 0, 10, 0] {
+        legacy.extend_from_slice(&v.to_le_bytes());
+    }
+
+    legacy.extend_from_slice(&0_u32.to_le_bytes());
+    let res = DynTable::load(&opts, Cursor::new(legacy));
+    assert_eq!(res.err(), Some(Error::MissingSchema));
+
+    let res = DynTable::new(&opts, &Record::default(), Cursor::new(vec![]));
+    assert_eq!(res.err(), Some(Error::MissingSchema));
+
+    let mut buf = t.into_inner().into_inner();
+    let name = buf.windows(6).position(|w| w == b"Sample").unwrap();
+    buf[name - 5] = 9;
+    let res = DynTable::load(&opts, Cursor::new(buf));
+    assert_eq!(res.err(), Some(Error::InvalidSchema));
+}
+
+#[test]
+fn struct_named_option() {
+    let schema = Schema::Struct(
+        "Option".to_string(),
+        vec![
+            ("some".to_string(), Schema::Primitive(Primitive::Bool)),
+            ("value".to_string(), Schema::Primitive(Primitive::U16)),
+        ],
+    );
+    let opts = Options::new(0, 10, 100);
+    let mut dp = Record::new(&schema);
+    dp.set("value", Value::U16(5)).unwrap();
+    let mut t = DynTable::new(&opts, &dp, Cursor::new(vec![])).unwrap();
+    t.insert(10, &dp).unwrap();
+
+    let buf = t.into_inner().into_inner();
+    let mut t = DynTable::load(&opts, Cursor::new(buf)).unwrap();
+    assert_eq!(t.schema(), &schema);
+    assert_eq!(
+        t.get(10).unwrap().unwrap().get("value"),
+        Some(Value::U16(5))
+    );
+}
+
+#[test]
+fn updates() {
+    let opts = Options::new(0, 10, 100);
+    let schema = Sample::default().get_schema();
+    let mut t = DynTable::new(&opts, &record(&schema, &sample(0)), Cursor::new(vec![])).unwrap();
+
+    for i in 1..5 {
+        t.insert(i * 10, &record(&schema, &sample(i))).unwrap();
+    }
+
+    t.update_at(20, &record(&schema, &sample(7))).unwrap();
+    let dps = [record(&schema, &sample(8)), record(&schema, &sample(9))];
+    t.update_range(30, &dps).unwrap();
+
+    let rows: Vec<_> = t.iter().unwrap().collect();
+    let expected: Vec<_> = [(0, 0), (10, 1), (20, 7), (30, 8), (40, 9)]
+        .into_iter()
+        .map(|(t, i)| (t, Some(record(&schema, &sample(i)))))
+        .collect();
+    assert_eq!(rows, expected);
+
+    let link = Record::new(&Link::default().get_schema());
+    let diff = "`load[0]` is stored as f32 but `rx` is u64";
+    let diff = Some(Error::SchemaMismatch(diff.to_string()));
+    assert_eq!(t.update_at(20, &link).err(), diff);
+    assert_eq!(t.update_range(20, &[t.record(), link]).err(), diff);
+    assert_eq!(t.get(20).unwrap(), Some(&record(&schema, &sample(7))));
+}
@@ -28,11 +28,11 @@ fn in_memory() {
     let buf = tab.into_inner().into_inner();
     let mut tab2 = rt::load::from_buffer(opts, buf).unwrap();
 
-    assert_eq!((47030060, &10000001), tab2.first().unwrap());
-    assert_eq!((47031092, &10000009), tab2.last().unwrap());
+    assert_eq!((47030060, Some(&10000001)), tab2.first().unwrap());
+    assert_eq!((47031092, Some(&10000009)), tab2.last().unwrap());
 
     for (t, v) in test_data.iter() {
-        assert_eq!(v, tab2.get(*t).unwrap());
+        assert_eq!(Some(v), tab2.get(*t).unwrap());
     }
 }
 
@@ -65,10 +65,10 @@ fn in_file() {
     std::mem::drop(file);
     let mut tab2 = rt::load::from_file(opts, "test.rtdb").unwrap();
 
-    assert_eq!((47029931, &10000000), tab2.first().unwrap());
-    assert_eq!((47031092, &10000009), tab2.last().unwrap());
+    assert_eq!((47029931, Some(&10000000)), tab2.first().unwrap());
+    assert_eq!((47031092, Some(&10000009)), tab2.last().unwrap());
 
     for (t, v) in test_data.iter() {
-        assert_eq!(v, tab2.get(*t).unwrap());
+        assert_eq!(Some(v), tab2.get(*t).unwrap());
     }
 }
@@ -108,3 +108,49 @@ fn different_structs_are_different() {
     assert_ne!(bar.get_hash(), baz.get_hash());
     assert_ne!(baz.get_hash(), foo.get_hash());
 }
+
+#[test]
+fn fingerprints_see_order_and_names() {
+    roundtable::datapoint! {
+        struct MemInfo {
+            total: u64,
+            free: u64,
+            avail: u64,
+        }
+
+        struct Swapped {
+            total: u64,
+            avail: u64,
+            free: u64,
+        }
+
+        struct Renamed {
+            total: u64,
+            unused: u64,
+            avail: u64,
+        }
+
+        struct Nested {
+            total: u64,
+            rest: Rest,
+        }
+
+        struct Rest {
+            free: u64,
+            avail: u64,
+        }
+    }
+
+    let mem = MemInfo::default();
+    let swapped = Swapped::default();
+    let renamed = Renamed::default();
+    let nested = Nested::default();
+    assert_eq!(mem.get_hash(), swapped.get_hash());
+    assert_eq!(mem.get_hash(), renamed.get_hash());
+    assert_eq!(mem.get_hash(), nested.get_hash());
+    assert_ne!(mem.get_fingerprint(), swapped.get_fingerprint());
+    assert_ne!(mem.get_fingerprint(), renamed.get_fingerprint());
+    assert_ne!(mem.get_fingerprint(), nested.get_fingerprint());
+    assert_ne!([0_u64; 3].get_fingerprint(), mem.get_fingerprint());
+    assert_eq!(mem.get_fingerprint(), MemInfo::default().get_fingerprint());
+}
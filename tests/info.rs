@@ -0,0 +1,114 @@
+use roundtable as rt;
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::rtdb::{TableInfo, VERSION};
+use std::io::Cursor;
+
+#[test]
+fn table_info() {
+    let opts = Options::new(0, 10, 40).archive(Consolidation::Max, 0.5, 20, 80);
+    let mut t = rt::create::in_memory(opts, 0_u32).unwrap();
+    t.insert(10, &1).unwrap();
+
+    let info = t.info();
+    assert_eq!(info.magic, 0x56445452);
+    assert_eq!(info.version, VERSION);
+    assert_eq!(info.dp_size, 4);
+    assert_eq!(info.dp_hash, 0_u32.get_fingerprint());
+    assert_eq!(info.dp_count, 4);
+    assert_eq!((info.t_start, info.t_step), (0, 10));
+    assert_eq!((info.t_first, info.t_updated), (0, 10));
+    assert_eq!(info.archive_count, 1);
+    assert_eq!(info.schema, Some(0_u32.get_schema()));
+    assert_eq!(info.capacity(), 40);
+    assert_eq!(info.filled(), 2);
+    assert_eq!(info.fill_level(), 0.5);
+
+    for i in 2..8 {
+        t.insert(i * 10, &(i as u32)).unwrap();
+    }
+
+    let info = t.info();
+    assert_eq!((info.t_first, info.t_updated), (40, 70));
+    assert_eq!(info.filled(), 4);
+    assert_eq!(info.fill_level(), 1.0);
+
+    let mut data = t.into_inner();
+    assert_eq!(TableInfo::peek(&mut data).unwrap(), info);
+}
+
+#[test]
+fn peek_file() {
+    let path = std::env::temp_dir().join("roundtable-peek.rtdb");
+    let opts = Options::new(100, 5, 50).overwrite(true);
+    let mut t = rt::create::in_file(opts, (0_u8, 0.0_f32), &path).unwrap();
+    t.insert(112, &(1, 2.0)).unwrap();
+    let info = t.info();
+    drop(t);
+
+    let peeked = rt::load::peek_file(&path).unwrap();
+    assert_eq!(peeked, info);
+    assert_eq!(peeked.t_updated, 112);
+    assert_eq!(peeked.filled(), 3);
+    assert_eq!(peeked.schema.unwrap().to_string(), "(u8, f32)");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn journaled() {
+    let opts = Options::new(0, 10, 40)
+        .journal(true)
+        .checksums(true)
+        .pending(Consolidation::Average)
+        .source(SourceKind::Counter)
+        .archive(Consolidation::Average, 0.5, 20, 80)
+        .archive(Consolidation::Max, 0.5, 40, 160);
+    let mut t = rt::create::in_memory(opts, (0_u8, 0.0_f32)).unwrap();
+    t.insert(10, &(1, 1.0)).unwrap();
+    t.insert(20, &(2, 3.0)).unwrap();
+    t.tune(&Options::new(0, 10, 40).max_fwd_skip(1)).unwrap();
+
+    let info = t.info();
+    assert_eq!((info.t_first, info.t_updated), (0, 20));
+    assert_eq!(info.filled(), 3);
+    assert_eq!(info.max_fwd_skip, Some(1));
+
+    let mut data = t.into_inner();
+    assert_eq!(TableInfo::peek(&mut data).unwrap(), info);
+}
+
+#[test]
+fn invalid_headers() {
+    let mut legacy = vec![];
+    legacy.extend_from_slice(&0x42445452_u32.to_le_bytes());
+
+    for v in [4, 0_u32.get_hash(), 4, 0, 10, 20] {
+        legacy.extend_from_slice(&v.to_le_bytes());
+    }
+
+    let info = TableInfo::peek(&mut Cursor::new(legacy)).unwrap();
+    assert_eq!((info.magic, info.version), (0x42445452, 1));
+    assert_eq!((info.t_updated, info.filled()), (20, 3));
+    assert_eq!(info.schema, None);
+
+    let opts = Options::new(0, 10, 40);
+    let buf = rt::create::in_memory(opts, 0_u32)
+        .unwrap()
+        .into_inner()
+        .into_inner();
+
+    let mut magic = buf.clone();
+    magic[0] = 0;
+    let res = TableInfo::peek(&mut Cursor::new(magic));
+    assert_eq!(res.err(), Some(Error::InvalidMagicNumber));
+
+    let mut step = buf.clone();
+    step[40..48].copy_from_slice(&0_u64.to_le_bytes());
+    let res = TableInfo::peek(&mut Cursor::new(step));
+    assert_eq!(res.err(), Some(Error::InvalidTimeStep));
+
+    let mut start = buf;
+    start[32..40].copy_from_slice(&100_u64.to_le_bytes());
+    let res = TableInfo::peek(&mut Cursor::new(start));
+    assert_eq!(res.err(), Some(Error::InvalidHeader));
+}
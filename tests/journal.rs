@@ -0,0 +1,139 @@
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::rtdb::Table;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+/// Accepts `budget` bytes of writes and fails every write after that,
+/// leaving the underlying buffer as it would be after a power loss.
+struct Crash {
+    inner: Cursor<Vec<u8>>,
+    budget: usize,
+}
+
+impl Read for Crash {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for Crash {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.budget == 0 {
+            return Err(io::Error::other("crashed"));
+        }
+
+        let n = buf.len().min(self.budget);
+        self.budget -= n;
+        self.inner.write(&buf[..n])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Seek for Crash {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+type Snapshot = (u64, Vec<(u64, Option<u32>)>, Vec<(u64, Option<u32>)>);
+
+fn snapshot(opts: &Options, buf: Vec<u8>) -> Snapshot {
+    let mut t = Table::load(opts, &0_u32, Cursor::new(buf)).unwrap();
+    let last = t.last().unwrap().0;
+    let primary = t.iter().unwrap().collect();
+    let archive = t.range(0, last).unwrap().collect();
+    t.verify().unwrap();
+    (last, primary, archive)
+}
+
+#[test]
+fn reload() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 40)
+        .journal(true)
+        .archive(Consolidation::Sum, 0.0, 20, 200);
+    let mut t = Table::new(&opts, &1_u32, buf).unwrap();
+    t.insert(10, &2).unwrap();
+    t.insert(20, &3).unwrap();
+    t.update_at(10, &5).unwrap();
+
+    let mut t2 = Table::load(&opts, &0_u32, t.into_inner()).unwrap();
+    assert_eq!(t2.last().unwrap(), (20, Some(&3)));
+    assert_eq!(t2.get(10).unwrap(), Some(&5));
+    t2.insert(30, &4).unwrap();
+    t2.insert(40, &6).unwrap();
+
+    let mut t3 = Table::load(&opts, &0_u32, t2.into_inner()).unwrap();
+    let rows: Vec<_> = t3.range(0, 40).unwrap().collect();
+    assert_eq!(rows, vec![(0, Some(6)), (20, Some(7))]);
+}
+
+#[test]
+fn rejected_writes() {
+    let opts = Options::new(0, 10, 100).journal(true).checksums(true);
+    let mut t = Table::new(&opts, &1_u32, Cursor::new(vec![])).unwrap();
+
+    for i in 1..12 {
+        t.insert(i * 10, &(i as u32)).unwrap();
+    }
+
+    let before = t.into_inner().into_inner();
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(before.clone())).unwrap();
+    assert_eq!(t.insert(110, &9).err(), Some(Error::UpdateTooEarly));
+    assert_eq!(t.insert(150, &9).err(), Some(Error::MaxSkipExceeded));
+    assert_eq!(t.insert(300, &9).err(), Some(Error::UpdateTooLate));
+    assert_eq!(t.update_at(10, &9).err(), Some(Error::OutOfRangePast));
+    assert_eq!(t.update_at(120, &9).err(), Some(Error::OutOfRangeFuture));
+    let after = t.into_inner().into_inner();
+    assert_eq!(after, before);
+
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(after)).unwrap();
+    assert_eq!(t.last().unwrap(), (110, Some(&11)));
+    t.insert(120, &12).unwrap();
+    assert_eq!(t.get(110).unwrap(), Some(&11));
+}
+
+#[test]
+fn crash_during_insert() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 40)
+        .journal(true)
+        .pending(Consolidation::Max)
+        .checksums(true)
+        .archive(Consolidation::Sum, 0.0, 20, 200);
+    let mut t = Table::new(&opts, &1_u32, buf).unwrap();
+
+    for i in 1..6 {
+        t.insert(i * 10, &(i as u32)).unwrap();
+    }
+
+    let before = t.into_inner().into_inner();
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(before.clone())).unwrap();
+    t.insert(80, &8).unwrap();
+    let after = t.into_inner().into_inner();
+    let old = snapshot(&opts, before.clone());
+    let new = snapshot(&opts, after);
+    assert_ne!(old, new);
+    let mut budget = 0;
+
+    loop {
+        let data = Crash {
+            inner: Cursor::new(before.clone()),
+            budget,
+        };
+        let mut t = Table::load(&opts, &0_u32, data).unwrap();
+        let done = t.insert(80, &8).is_ok();
+        let state = snapshot(&opts, t.into_inner().inner.into_inner());
+
+        if done {
+            assert_eq!(state, new);
+            break;
+        }
+
+        assert!(state == old || state == new, "budget {budget}");
+        budget += 1;
+    }
+}
@@ -154,3 +154,80 @@ fn nested_structs() {
     assert_eq!(QUX, new);
     assert_eq!(QUX.get_hash(), new.get_hash());
 }
+
+#[test]
+fn other_types() {
+    roundtable::datapoint! {
+        struct Foo {
+            up: bool,
+            grade: char,
+            temp: Option<f32>,
+            pair: (u8, i16),
+            grid: [[u16; 2]; 3],
+            links: [Bar; 2],
+        }
+
+        struct Bar {
+            rx: u32,
+            tx: u32,
+        }
+    }
+
+    let orig = Foo {
+        up: true,
+        grade: 'B',
+        temp: Some(21.5),
+        pair: (3, -4),
+        grid: [[1, 2], [3, 4], [5, 6]],
+        links: [Bar { rx: 1, tx: 2 }, Bar { rx: 3, tx: 4 }],
+    };
+
+    let mut buf = Cursor::new(vec![]);
+    assert_eq!(orig.get_size(), 1 + 4 + 5 + 3 + 12 + 16);
+    orig.write_out(&mut buf).unwrap();
+    assert_eq!(buf.get_ref().len() as u64, orig.get_size());
+    buf.rewind().unwrap();
+    let mut new = Foo::default();
+    new.read_in(&mut buf).unwrap();
+    assert_eq!(orig, new);
+
+    let absent = Foo { temp: None, ..orig };
+    assert_eq!(absent.get_size(), orig.get_size());
+    assert_eq!(absent.get_hash(), orig.get_hash());
+    assert_eq!(absent.get_schema(), orig.get_schema());
+    assert_eq!(
+        orig.get_schema().to_string(),
+        "Foo { up: bool, grade: char, temp: Option<f32>, \
+         pair: (u8, i16), grid: [[u16; 2]; 3], links: [Bar { rx: u32, tx: u32 }; 2] }"
+    );
+
+    assert_eq!(true.get_hash(), 9223355547829397123);
+    assert_eq!('a'.get_hash(), 18446740782472755943);
+    assert_eq!(
+        [(1_u8, 'x'); 2].get_hash(),
+        (3_u8, 'y', 4_u8, 'z').get_hash()
+    );
+}
+
+#[test]
+fn other_types_in_tables() {
+    let opts = Options::new(0, 10, 100).pending(Consolidation::Average);
+    let mut t = roundtable::create::in_memory(opts, (false, Some(0_u32))).unwrap();
+    t.insert(12, &(true, Some(100))).unwrap();
+    t.insert(16, &(true, None)).unwrap();
+    t.insert(18, &(false, Some(300))).unwrap();
+    t.insert(19, &(false, None)).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&(true, Some(200))));
+
+    let mut v = Some(0_u32);
+    v.lerp(&Some(10), &Some(20), 1, 4);
+    assert_eq!(v, Some(12));
+    v.lerp(&Some(10), &None, 1, 4);
+    assert_eq!(v, Some(10));
+    v.lerp(&Some(10), &None, 2, 4);
+    assert_eq!(v, None);
+    v.rate(&Some(10), &Some(30), SourceKind::Counter, 10);
+    assert_eq!(v, Some(2));
+    v.rate(&None, &Some(30), SourceKind::Counter, 10);
+    assert_eq!(v, None);
+}
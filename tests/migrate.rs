@@ -0,0 +1,98 @@
+use roundtable as rt;
+use roundtable::prelude::*;
+use roundtable::schema;
+use std::io::Cursor;
+
+roundtable::datapoint! {
+    struct Old {
+        rx: u32,
+        tx: i16,
+        errs: u8,
+    }
+
+    struct New {
+        rx: u64,
+        drops: u64,
+        tx: i8,
+    }
+}
+
+fn old(i: u64) -> Old {
+    Old {
+        rx: i as u32 * 100,
+        tx: -(i as i16) * 50,
+        errs: 1,
+    }
+}
+
+#[test]
+fn by_name() {
+    let mut map = schema::by_name::<Old, New>();
+    let new = map(&Old {
+        rx: u32::MAX,
+        tx: -300,
+        errs: 7,
+    });
+    assert_eq!(
+        new,
+        New {
+            rx: u32::MAX as u64,
+            drops: 0,
+            tx: i8::MIN,
+        }
+    );
+}
+
+#[test]
+fn migrate() {
+    let opts =
+        Options::new(0, 10, 40)
+            .track_unknown(true)
+            .archive(Consolidation::Max, 0.5, 20, 200);
+    let mut t = rt::create::in_memory(opts, old(0)).unwrap();
+
+    for i in [1, 2, 4, 5, 6, 7, 8] {
+        t.insert(i * 10, &old(i)).unwrap();
+    }
+
+    let t = t.migrate(&opts, Cursor::new(vec![]), schema::by_name::<Old, New>());
+    let mut t = t.unwrap();
+    let new = |i: u64| New {
+        rx: i * 100,
+        drops: 0,
+        tx: (-(i as i64) * 50).max(-128) as i8,
+    };
+    assert_eq!(t.first().unwrap(), (50, Some(&new(5))));
+    assert_eq!(t.last().unwrap(), (80, Some(&new(8))));
+    let rows: Vec<_> = t.range(0, 80).unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![
+            (0, Some(New { tx: 0, ..new(1) })),
+            (20, Some(New { tx: -100, ..new(4) })),
+            (40, Some(new(5))),
+            (60, Some(new(7)))
+        ]
+    );
+
+    t.insert(90, &new(9)).unwrap();
+    let buf = t.into_inner().into_inner();
+    let mut t = rt::load::from_buffer::<New, _>(opts, buf).unwrap();
+    assert_eq!(t.get(80).unwrap(), Some(&new(8)));
+    assert_eq!(t.get(90).unwrap(), Some(&new(9)));
+}
+
+#[test]
+fn pending_and_custom_mapping() {
+    let opts = Options::new(0, 10, 40).pending(Consolidation::Average);
+    let mut t = rt::create::in_memory(opts, 0_u32).unwrap();
+    t.insert(10, &10).unwrap();
+    t.insert(12, &20).unwrap();
+
+    let mut t = t
+        .migrate(&opts, Cursor::new(vec![]), |v: &u32| *v as f64 / 2.0)
+        .unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&8.0));
+    t.insert(14, &20.0).unwrap();
+    assert_eq!(t.get(10).unwrap(), Some(&12.8));
+}
@@ -0,0 +1,70 @@
+use roundtable as rt;
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use std::path::PathBuf;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(name);
+    _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn open_or_create() {
+    let dir = temp_dir("roundtable-open");
+    let path = dir.join("table.rtdb");
+    let opts = Options::new(0, 10, 100);
+    let mut t = rt::create::open_or_create(opts, 1_u32, &path).unwrap();
+    t.insert(10, &2).unwrap();
+    drop(t);
+
+    let later = Options::new(500, 10, 100).overwrite(true);
+    let mut t = rt::create::open_or_create(later, 7_u32, &path).unwrap();
+    assert_eq!(t.first().unwrap(), (0, Some(&1)));
+    assert_eq!(t.last().unwrap(), (10, Some(&2)));
+    drop(t);
+
+    let res = rt::create::open_or_create(Options::new(0, 20, 200), 0_u32, &path);
+    let diff = "`t_step` is 20 but 10 is stored";
+    assert_eq!(res.err(), Some(Error::OptionsMismatch(diff.to_string())));
+
+    let res = rt::create::open_or_create(Options::new(0, 10, 50), 0_u32, &path);
+    let diff = "`dp_count` is 5 but 10 is stored";
+    assert_eq!(res.err(), Some(Error::OptionsMismatch(diff.to_string())));
+
+    let res = rt::create::open_or_create(opts, 0_i32, &path);
+    let diff = "data point is stored as u32 but data point is i32";
+    assert_eq!(res.err(), Some(Error::SchemaMismatch(diff.to_string())));
+
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(files.len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn concurrent_creation() {
+    let dir = temp_dir("roundtable-open-concurrent");
+    let path = dir.join("table.rtdb");
+    let opts = Options::new(0, 10, 100);
+
+    let handles: Vec<_> = (0..8_u64)
+        .map(|i| {
+            let path = path.clone();
+            std::thread::spawn(move || {
+                let t = rt::create::open_or_create(opts, i, &path).unwrap();
+                t.info().t_updated
+            })
+        })
+        .collect();
+
+    for h in handles {
+        assert_eq!(h.join().unwrap(), 0);
+    }
+
+    let mut t = rt::load::from_file::<u64, _>(opts, &path).unwrap();
+    assert!(t.get(0).unwrap().is_some_and(|v| *v < 8));
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(files.len(), 1);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,140 @@
+use roundtable as rt;
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::rtdb::{Table, TableInfo};
+use std::io::Cursor;
+
+#[test]
+fn stored_skip_settings() {
+    let opts = Options::new(0, 10, 100)
+        .max_fwd_skip(5)
+        .fwd_skip_mode(FwdSkipMode::Linear);
+    let mut t = rt::create::in_memory(opts, 0_i32).unwrap();
+    t.insert(10, &10).unwrap();
+    let info = t.info();
+    assert_eq!(info.max_fwd_skip, Some(5));
+    assert_eq!(info.fwd_skip_mode, Some(FwdSkipMode::Linear));
+
+    let buf = t.into_inner().into_inner();
+    let mut t = rt::load::from_buffer::<i32, _>(Options::new(0, 10, 100), buf).unwrap();
+    t.insert(70, &70).unwrap();
+    assert_eq!(t.get(40).unwrap(), Some(&40));
+
+    let buf = t.into_inner().into_inner();
+    let res = rt::load::from_buffer::<i32, _>(opts.max_fwd_skip(3), buf.clone());
+    let diff = "`max_fwd_skip` is 3 but 5 is stored";
+    assert_eq!(res.err(), Some(Error::OptionsMismatch(diff.to_string())));
+
+    let res = rt::load::from_buffer::<i32, _>(opts.fwd_skip_mode(FwdSkipMode::Zeroed), buf);
+    let diff = "`fwd_skip_mode` is Zeroed but Linear is stored";
+    assert_eq!(res.err(), Some(Error::OptionsMismatch(diff.to_string())));
+}
+
+#[test]
+fn tune() {
+    let opts = Options::new(0, 10, 100);
+    let mut t = rt::create::in_memory(opts, 0_i32).unwrap();
+    t.insert(10, &10).unwrap();
+    let res = t.insert(50, &50);
+    assert_eq!(res.err(), Some(Error::MaxSkipExceeded));
+
+    let res = t.tune(&Options::new(0, 10, 100).max_fwd_skip(9));
+    assert_eq!(res.err(), Some(Error::InvalidSkip));
+    let res = t.tune(&Options::new(0, 10, 100).fwd_skip_mode(FwdSkipMode::Unknown));
+    assert_eq!(res.err(), Some(Error::InvalidSkipMode));
+
+    let tuned = Options::new(0, 10, 100)
+        .max_fwd_skip(4)
+        .fwd_skip_mode(FwdSkipMode::Zeroed);
+    t.tune(&tuned).unwrap();
+    t.insert(50, &50).unwrap();
+    assert_eq!(t.get(30).unwrap(), Some(&0));
+
+    let mut data = t.into_inner();
+    let info = TableInfo::peek(&mut data).unwrap();
+    assert_eq!(info.max_fwd_skip, Some(4));
+    assert_eq!(info.fwd_skip_mode, Some(FwdSkipMode::Zeroed));
+
+    let mut t = Table::load(&tuned, &0_i32, data).unwrap();
+    t.tune(&Options::new(0, 10, 100).max_fwd_skip(1)).unwrap();
+    let res = t.insert(80, &80);
+    assert_eq!(res.err(), Some(Error::MaxSkipExceeded));
+    assert_eq!(t.info().fwd_skip_mode, Some(FwdSkipMode::Zeroed));
+}
+
+#[test]
+fn tune_journaled() {
+    let opts = Options::new(0, 10, 100).journal(true);
+    let mut t = rt::create::in_memory(opts, 0_i32).unwrap();
+    t.insert(10, &10).unwrap();
+    t.tune(&opts.max_fwd_skip(5)).unwrap();
+    let buf = t.into_inner().into_inner();
+
+    let mut t = rt::load::from_buffer::<i32, _>(opts.max_fwd_skip(5), buf.clone()).unwrap();
+    assert_eq!(t.info().max_fwd_skip, Some(5));
+    t.insert(70, &70).unwrap();
+
+    let res = rt::load::from_buffer::<i32, _>(opts.max_fwd_skip(2), buf.clone());
+    let diff = "`max_fwd_skip` is 2 but 5 is stored";
+    assert_eq!(res.err(), Some(Error::OptionsMismatch(diff.to_string())));
+
+    let t = rt::load::from_buffer::<i32, _>(opts.strict(true), buf).unwrap();
+    assert_eq!(t.info().max_fwd_skip, Some(5));
+}
+
+#[test]
+fn strict() {
+    let opts = Options::new(0, 10, 100)
+        .heartbeat(30)
+        .pending(Consolidation::Max)
+        .archive(Consolidation::Average, 0.5, 20, 200);
+    let t = rt::create::in_memory(opts, 0_u16).unwrap();
+    t.check_options(&opts).unwrap();
+    t.check_options(&Options::new(50, 10, 100)).unwrap_err();
+    let buf = t.into_inner().into_inner();
+
+    let mut t = rt::load::from_buffer::<u16, _>(opts.strict(true), buf.clone()).unwrap();
+    t.insert(10, &1).unwrap();
+
+    let res = rt::load::from_buffer::<u16, _>(opts.heartbeat(40).strict(true), buf.clone());
+    let diff = "`heartbeat` is 40 but 30 is stored";
+    assert_eq!(res.err(), Some(Error::OptionsMismatch(diff.to_string())));
+
+    let other = Options::new(0, 10, 100)
+        .heartbeat(30)
+        .pending(Consolidation::Max)
+        .archive(Consolidation::Max, 0.5, 20, 200)
+        .strict(true);
+    let res = rt::load::from_buffer::<u16, _>(other, buf.clone());
+    let diff = "`archive 0` is Max, 0.5, 20, 10 rows but Average, 0.5, 20, 10 rows is stored";
+    assert_eq!(res.err(), Some(Error::OptionsMismatch(diff.to_string())));
+
+    let mut t = rt::load::from_buffer::<u16, _>(other.strict(false), buf).unwrap();
+    t.insert(10, &1).unwrap();
+}
+
+#[test]
+fn legacy_tables() {
+    let mut buf = vec![];
+    buf.extend_from_slice(&0x42445452_u32.to_le_bytes());
+
+    for v in [4, 0_u32.get_hash(), 10, 0, 10, 0] {
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    buf.extend_from_slice(&0_u32.to_le_bytes());
+    let opts = Options::new(0, 10, 100)
+        .max_fwd_skip(4)
+        .fwd_skip_mode(FwdSkipMode::Linear);
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(buf)).unwrap();
+    assert_eq!(t.info().max_fwd_skip, None);
+    t.insert(50, &50).unwrap();
+    assert_eq!(t.get(20).unwrap(), Some(&20));
+
+    let t = t.upgrade(&opts, Cursor::new(vec![])).unwrap();
+    let mut t = Table::load(&Options::new(0, 10, 100), &0_u32, t.into_inner()).unwrap();
+    assert_eq!(t.info().max_fwd_skip, Some(4));
+    assert_eq!(t.info().fwd_skip_mode, Some(FwdSkipMode::Linear));
+    t.insert(100, &100).unwrap();
+    assert_eq!(t.get(70).unwrap(), Some(&70));
+}
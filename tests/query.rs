@@ -0,0 +1,111 @@
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::rtdb::Table;
+use std::io::Cursor;
+
+#[test]
+fn aggregate_primitive() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 100);
+    let mut t = Table::new(&opts, &0_u16, buf).unwrap();
+
+    for i in 1..15 {
+        t.insert(i * 10, &(i as u16 * 100)).unwrap();
+    }
+
+    let a = t.aggregate(60, 125).unwrap();
+    assert_eq!(a.count(), 7);
+    assert_eq!(a.min(), 600);
+    assert_eq!(a.max(), 1200);
+    assert_eq!(a.mean(), 900);
+    assert_eq!(a.sum(), 6300);
+    assert_eq!(a.last(), 1200);
+    assert_eq!(t.aggregate(40, 60).unwrap_err(), Error::OutOfRangePast);
+    assert_eq!(t.aggregate(60, 141).unwrap_err(), Error::OutOfRangeFuture);
+}
+
+#[test]
+fn aggregate_struct() {
+    roundtable::datapoint! {
+        struct Foo {
+            a: i32,
+            b: [f64; 2],
+        }
+    }
+
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 100);
+    let mut t = Table::new(&opts, &Foo::default(), buf).unwrap();
+    t.insert(
+        10,
+        &Foo {
+            a: -5,
+            b: [1.0, 8.0],
+        },
+    )
+    .unwrap();
+    t.insert(
+        20,
+        &Foo {
+            a: 5,
+            b: [2.0, 4.0],
+        },
+    )
+    .unwrap();
+    t.insert(
+        30,
+        &Foo {
+            a: 30,
+            b: [3.0, 0.0],
+        },
+    )
+    .unwrap();
+
+    let a = t.aggregate(10, 30).unwrap();
+    assert_eq!(a.count(), 3);
+    assert_eq!(
+        a.min(),
+        Foo {
+            a: -5,
+            b: [1.0, 0.0]
+        }
+    );
+    assert_eq!(
+        a.max(),
+        Foo {
+            a: 30,
+            b: [3.0, 8.0]
+        }
+    );
+    assert_eq!(
+        a.mean(),
+        Foo {
+            a: 10,
+            b: [2.0, 4.0]
+        }
+    );
+    assert_eq!(
+        a.get(Consolidation::Sum),
+        Foo {
+            a: 30,
+            b: [6.0, 12.0]
+        }
+    );
+}
+
+#[test]
+fn quantile() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 1, 200);
+    let mut t = Table::new(&opts, &[0_i64, 0], buf).unwrap();
+
+    for i in 1..=100 {
+        t.insert(i, &[i as i64, -(i as i64)]).unwrap();
+    }
+
+    assert_eq!(t.quantile(1, 100, 0.5).unwrap(), [50, -51]);
+    assert_eq!(t.quantile(1, 100, 0.95).unwrap(), [95, -6]);
+    assert_eq!(t.quantile(1, 100, 1.0).unwrap(), [100, -1]);
+    assert_eq!(t.quantile(0, 100, 0.0).unwrap(), [0, -100]);
+    assert_eq!(t.quantile(50, 50, 0.99).unwrap(), [50, -50]);
+}
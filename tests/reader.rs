@@ -0,0 +1,152 @@
+use roundtable as rt;
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::reader::TableReader;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+
+/// Accepts `budget` bytes of writes and fails every write after that.
+struct Crash {
+    inner: Cursor<Vec<u8>>,
+    budget: usize,
+}
+
+impl Read for Crash {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.inner.read(buf)
+    }
+}
+
+impl Write for Crash {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.budget == 0 {
+            return Err(io::Error::other("crashed"));
+        }
+
+        let n = buf.len().min(self.budget);
+        self.budget -= n;
+        self.inner.write(&buf[..n])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl Seek for Crash {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn read_buffer() {
+    let opts = Options::new(0, 10, 40).archive(Consolidation::Sum, 0.0, 20, 200);
+    let mut t = rt::create::in_memory(opts, 1_u32).unwrap();
+
+    for i in 1..6 {
+        t.insert(i * 10, &(i as u32)).unwrap();
+    }
+
+    let archive: Vec<_> = t.range(0, 50).unwrap().collect();
+    let buf = t.into_inner().into_inner();
+    let mut r = TableReader::load(&opts, &0_u32, Cursor::new(&buf[..])).unwrap();
+    assert_eq!(r.first().unwrap(), (20, Some(&2)));
+    assert_eq!(r.last().unwrap(), (50, Some(&5)));
+    assert_eq!(r.get(30).unwrap(), Some(&3));
+
+    let rows: Vec<_> = r.iter().unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![(20, Some(2)), (30, Some(3)), (40, Some(4)), (50, Some(5))]
+    );
+    let rows: Vec<_> = r.range(0, 50).unwrap().collect();
+    assert_eq!(rows, archive);
+    assert_eq!(r.aggregate(20, 50).unwrap().max(), 5);
+    assert_eq!(r.info().t_updated, 50);
+    assert_eq!(r.schema(), Some(&0_u32.get_schema()));
+    r.verify().unwrap();
+}
+
+#[test]
+fn read_file() {
+    let path = std::env::temp_dir().join("roundtable-reader.rtdb");
+    let opts = Options::new(0, 10, 40).overwrite(true);
+    let mut t = rt::create::in_file(opts, 1_u16, &path).unwrap();
+    t.insert(10, &2).unwrap();
+    drop(t);
+
+    let mut perms = std::fs::metadata(&path).unwrap().permissions();
+    perms.set_readonly(true);
+    std::fs::set_permissions(&path, perms.clone()).unwrap();
+
+    let mut r = rt::load::read_file::<u16, _>(opts, &path).unwrap();
+    assert_eq!(r.last().unwrap(), (10, Some(&2)));
+    assert_eq!(r.info(), rt::load::peek_file(&path).unwrap());
+    drop(r);
+
+    #[allow(clippy::permissions_set_readonly_false)]
+    perms.set_readonly(false);
+    std::fs::set_permissions(&path, perms).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn pending_redo() {
+    let opts = Options::new(0, 10, 40)
+        .journal(true)
+        .checksums(true)
+        .archive(Consolidation::Sum, 0.0, 20, 200);
+    let mut t = rt::create::in_memory(opts, 1_u32).unwrap();
+
+    for i in 1..6 {
+        t.insert(i * 10, &(i as u32)).unwrap();
+    }
+
+    let before = t.into_inner().into_inner();
+    let mut pending = 0;
+
+    for budget in 0.. {
+        let data = Crash {
+            inner: Cursor::new(before.clone()),
+            budget,
+        };
+        let mut t = Table::load(&opts, &0_u32, data).unwrap();
+        let done = t.insert(80, &8).is_ok();
+        let buf = t.into_inner().inner.into_inner();
+
+        match TableReader::load(&opts, &0_u32, Cursor::new(&buf)) {
+            Ok(mut r) => {
+                let last = r.last().unwrap().0;
+                assert!(last == 50 || last == 80, "budget {budget}");
+                r.verify().unwrap();
+            }
+            Err(e) => {
+                assert_eq!(e, Error::PendingRedo);
+                pending += 1;
+                let t = rt::load::from_buffer::<u32, _>(opts, buf).unwrap();
+                let buf = t.into_inner().into_inner();
+                let mut r = TableReader::load(&opts, &0_u32, Cursor::new(buf)).unwrap();
+                assert_eq!(r.last().unwrap(), (80, Some(&8)));
+            }
+        }
+
+        if done {
+            break;
+        }
+    }
+
+    assert!(pending > 0);
+}
+
+#[test]
+fn after_rejected_write() {
+    let opts = Options::new(0, 10, 40).journal(true);
+    let mut t = rt::create::in_memory(opts, 1_u32).unwrap();
+    t.insert(10, &2).unwrap();
+    assert_eq!(t.insert(10, &3).err(), Some(Error::UpdateTooEarly));
+    assert_eq!(t.update_at(20, &3).err(), Some(Error::OutOfRangeFuture));
+
+    let buf = t.into_inner().into_inner();
+    let mut r = TableReader::load(&opts, &0_u32, Cursor::new(&buf[..])).unwrap();
+    assert_eq!(r.last().unwrap(), (10, Some(&2)));
+}
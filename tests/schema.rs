@@ -0,0 +1,130 @@
+use roundtable as rt;
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::rtdb::TableInfo;
+use roundtable::schema::{Leaf, Primitive, Schema};
+use std::io::Cursor;
+
+roundtable::datapoint! {
+    struct Link {
+        rx: u64,
+        tx: u64,
+    }
+
+    struct Host {
+        load: [f32; 3],
+        link: Link,
+    }
+
+    struct Narrow {
+        load: [f32; 3],
+        link: [u32; 4],
+    }
+
+    struct Float {
+        load: [f32; 3],
+        link: f64,
+        rest: u64,
+    }
+}
+
+#[test]
+fn macro_schema() {
+    let schema = Host::default().get_schema();
+    assert_eq!(
+        schema.to_string(),
+        "Host { load: [f32; 3], link: Link { rx: u64, tx: u64 } }"
+    );
+    assert_eq!(schema.get_size(), Host::default().get_size());
+
+    let leaves = schema.leaves();
+    assert_eq!(leaves.len(), 5);
+    assert_eq!(
+        leaves[4],
+        Leaf {
+            path: "link.tx".to_string(),
+            kind: Primitive::U64,
+            offset: 20,
+        }
+    );
+    assert_eq!(1_i16.get_schema(), Schema::Primitive(Primitive::I16));
+}
+
+#[test]
+fn stored_schema() {
+    let opts = Options::new(0, 10, 100).checksums(true);
+    let mut t = rt::create::in_memory(opts, Host::default()).unwrap();
+    t.insert(10, &Host::default()).unwrap();
+
+    let buf = t.into_inner().into_inner();
+    let t = rt::load::from_buffer::<Host, _>(opts, buf).unwrap();
+    assert_eq!(t.schema(), Some(&Host::default().get_schema()));
+}
+
+#[test]
+fn mismatch() {
+    let opts = Options::new(0, 10, 100);
+    let t = rt::create::in_memory(opts, Host::default()).unwrap();
+    let buf = t.into_inner().into_inner();
+
+    let res = rt::load::from_buffer::<Narrow, _>(opts, buf.clone());
+    let diff = "`link.rx` is stored as u64 but `link[0]` is u32";
+    assert_eq!(res.err(), Some(Error::SchemaMismatch(diff.to_string())));
+
+    let res = rt::load::from_buffer::<Float, _>(opts, buf.clone());
+    let diff = "`link.rx` is stored as u64 but `link` is f64";
+    assert_eq!(res.err(), Some(Error::SchemaMismatch(diff.to_string())));
+
+    let res = rt::load::from_buffer::<Float, _>(opts.ignore_hash(true), buf);
+    assert!(res.is_ok());
+}
+
+#[test]
+fn renamed_field() {
+    roundtable::datapoint! {
+        struct Renamed {
+            load: [f32; 3],
+            link: Link2,
+        }
+
+        struct Link2 {
+            tx: u64,
+            rx: u64,
+        }
+    }
+
+    let opts = Options::new(0, 10, 100);
+    let t = rt::create::in_memory(opts, Host::default()).unwrap();
+    let buf = t.into_inner().into_inner();
+    let res = rt::load::from_buffer::<Renamed, _>(opts, buf);
+    let diff = "`link.tx` is stored as `link.rx`";
+    assert_eq!(res.err(), Some(Error::SchemaMismatch(diff.to_string())));
+}
+
+#[test]
+fn unbounded_array() {
+    roundtable::datapoint! {
+        struct Padded {
+            value: u32,
+            pad: [[u32; 0]; 1],
+        }
+    }
+
+    let opts = Options::new(0, 10, 100);
+    let t = rt::create::in_memory(opts, Padded::default()).unwrap();
+    let buf = t.into_inner().into_inner();
+
+    let mut pad = vec![1];
+    pad.extend_from_slice(&1_u64.to_le_bytes());
+    pad.push(1);
+    pad.extend_from_slice(&0_u64.to_le_bytes());
+    let at = buf.windows(pad.len()).position(|w| w == pad).unwrap();
+
+    let mut huge = buf;
+    huge[at + 1..at + 9].copy_from_slice(&(1_u64 << 60).to_le_bytes());
+    let res = TableInfo::peek(&mut Cursor::new(huge.clone()));
+    assert_eq!(res.err(), Some(Error::InvalidSchema));
+
+    let res = rt::load::from_buffer::<Padded, _>(opts, huge);
+    assert_eq!(res.err(), Some(Error::InvalidSchema));
+}
@@ -0,0 +1,88 @@
+use roundtable as rt;
+use roundtable::prelude::*;
+use std::io::Cursor;
+
+fn rate<T: Rate + Default>(prev: T, cur: T, kind: SourceKind, dt: u64) -> T {
+    let mut res = T::default();
+    res.rate(&prev, &cur, kind, dt);
+    res
+}
+
+#[test]
+fn primitives() {
+    assert_eq!(rate(100_u32, 300, SourceKind::Gauge, 10), 300);
+    assert_eq!(rate(100_u32, 300, SourceKind::Counter, 10), 20);
+    assert_eq!(rate(u32::MAX - 9, 10, SourceKind::Counter, 10), 2);
+    assert_eq!(rate(u16::MAX - 9, 10, SourceKind::Counter, 5), 4);
+    assert_eq!(rate(u32::MAX as u64 - 9, 10, SourceKind::Counter, 10), 2);
+    assert_eq!(rate(u64::MAX - 19, 20, SourceKind::Counter, 10), 4);
+    assert_eq!(rate(300_i64, 100, SourceKind::Derive, 10), -20);
+    assert_eq!(rate(300_u64, 100, SourceKind::Derive, 10), 0);
+    assert_eq!(rate(0_u64, 50, SourceKind::Absolute, 10), 5);
+    assert_eq!(rate(1.0_f64, 4.0, SourceKind::Counter, 2), 1.5);
+    assert_eq!(rate(4.0_f64, 1.0, SourceKind::Derive, 2), -1.5);
+    assert_eq!(rate([0_u8, 10], [20, 0], SourceKind::Counter, 2), [10, 123]);
+}
+
+#[test]
+fn wide_integers() {
+    assert_eq!(rate(0_u128, u128::MAX, SourceKind::Counter, 1), u128::MAX);
+    assert_eq!(
+        rate(0_u128, u128::MAX, SourceKind::Derive, 5),
+        u128::MAX / 5
+    );
+    assert_eq!(rate(u128::MAX, 0, SourceKind::Derive, 1), 0);
+    assert_eq!(rate(u128::MAX, 10, SourceKind::Counter, 1), 11);
+    assert_eq!(rate(u128::MAX - 9, 10, SourceKind::Counter, 10), 2);
+    assert_eq!(
+        rate(u64::MAX as u128, 1 << 66, SourceKind::Counter, 1),
+        3 << 64 | 1
+    );
+    assert_eq!(
+        rate(u128::MAX, u128::MAX, SourceKind::Absolute, 3),
+        u128::MAX / 3
+    );
+    assert_eq!(rate(i128::MIN, i128::MAX, SourceKind::Derive, 1), i128::MAX);
+    assert_eq!(rate(i128::MAX, i128::MIN, SourceKind::Derive, 1), i128::MIN);
+    assert_eq!(rate(i128::MAX, i128::MIN, SourceKind::Counter, 1), 1);
+    assert_eq!(rate(-10_i128, -30, SourceKind::Derive, 10), -2);
+    assert_eq!(rate(i128::MIN, i128::MAX, SourceKind::Gauge, 7), i128::MAX);
+}
+
+#[test]
+fn counter() {
+    roundtable::datapoint! {
+        struct NetDev {
+            rx: u64,
+            tx: u64,
+        }
+    }
+
+    let opts = Options::new(0, 10, 100)
+        .track_unknown(true)
+        .source(SourceKind::Counter);
+    let mut t = rt::create::in_memory(opts, NetDev { rx: 1000, tx: 500 }).unwrap();
+    t.insert(10, &NetDev { rx: 2000, tx: 600 }).unwrap();
+    t.insert(20, &NetDev { rx: 2500, tx: 900 }).unwrap();
+    assert_eq!(t.get(0).unwrap(), None);
+    assert_eq!(t.get(10).unwrap(), Some(&NetDev { rx: 100, tx: 10 }));
+    assert_eq!(t.get(20).unwrap(), Some(&NetDev { rx: 50, tx: 30 }));
+
+    let buf = t.into_inner().into_inner();
+    let mut t2 = rt::load::from_buffer(opts, buf).unwrap();
+    t2.insert(40, &NetDev { rx: 4500, tx: 900 }).unwrap();
+    assert_eq!(t2.get(30).unwrap(), Some(&NetDev { rx: 100, tx: 0 }));
+    assert_eq!(t2.get(40).unwrap(), Some(&NetDev { rx: 100, tx: 0 }));
+}
+
+#[test]
+fn absolute() {
+    let buf = Cursor::new(vec![]);
+    let opts = Options::new(0, 10, 100).source(SourceKind::Absolute);
+    let mut t = Table::new(&opts, &0_u32, buf).unwrap();
+    t.insert(10, &50).unwrap();
+    t.insert(30, &80).unwrap();
+    assert_eq!(t.get(0).unwrap(), Some(&0));
+    assert_eq!(t.get(10).unwrap(), Some(&5));
+    assert_eq!(t.get(30).unwrap(), Some(&4));
+}
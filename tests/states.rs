@@ -0,0 +1,117 @@
+use roundtable as rt;
+use roundtable::data::Discrete;
+use roundtable::error::Error;
+use roundtable::prelude::*;
+
+roundtable::datapoint! {
+    enum State {
+        Up,
+        Degraded,
+        Down,
+    }
+
+    struct Service {
+        state: State,
+        latency: f32,
+    }
+}
+
+#[test]
+fn enums() {
+    assert_eq!(State::default(), State::Up);
+    assert_eq!(State::Down.get_size(), 4);
+    assert_eq!(State::from_index(1), Some(State::Degraded));
+    assert_eq!(State::from_index(3), None);
+    assert_eq!(
+        Service::default().get_schema().to_string(),
+        "Service { state: enum State { Up, Degraded, Down }, latency: f32 }"
+    );
+
+    roundtable::datapoint! {
+        enum Swapped {
+            Up,
+            Down,
+            Degraded,
+        }
+    }
+
+    assert_ne!(State::Up.get_hash(), Swapped::Up.get_hash());
+    assert_ne!(State::Up.get_hash(), 0_u32.get_hash());
+
+    let opts = Options::new(0, 10, 100);
+    let t = rt::create::in_memory(opts, State::Up).unwrap();
+    let buf = t.into_inner().into_inner();
+    let res = rt::load::from_buffer::<Swapped, _>(opts, buf);
+    let diff =
+        "data point has variants Up, Down, Degraded but the stored ones are Up, Degraded, Down";
+    assert_eq!(res.err(), Some(Error::SchemaMismatch(diff.to_string())));
+}
+
+#[test]
+fn nearest_and_consolidation() {
+    let opts = Options::new(0, 10, 100).fwd_skip_mode(FwdSkipMode::Linear);
+    let mut t = rt::create::in_memory(opts, Service::default()).unwrap();
+    let svc = |state, latency| Service { state, latency };
+    t.insert(10, &svc(State::Up, 1.0)).unwrap();
+    t.insert(40, &svc(State::Down, 4.0)).unwrap();
+    assert_eq!(t.get(20).unwrap(), Some(&svc(State::Up, 2.0)));
+    assert_eq!(t.get(30).unwrap(), Some(&svc(State::Down, 3.0)));
+
+    t.update_at(20, &svc(State::Degraded, 2.0)).unwrap();
+    let agg = t.aggregate(10, 40).unwrap();
+    assert_eq!(agg.mean().state, State::Down);
+    assert_eq!(agg.min().state, State::Up);
+    assert_eq!(agg.max().state, State::Down);
+    assert_eq!(agg.last().state, State::Down);
+    assert_eq!(t.quantile(10, 40, 0.5).unwrap().state, State::Degraded);
+
+    let opts = Options::new(0, 10, 40).archive(Consolidation::Max, 0.5, 40, 400);
+    let mut t = rt::create::in_memory(opts, State::Up).unwrap();
+    let states = [State::Up, State::Degraded, State::Up, State::Up];
+
+    for (i, s) in states.iter().cycle().take(12).enumerate() {
+        t.insert(10 * (i as u64 + 1), s).unwrap();
+    }
+
+    let rows: Vec<_> = t.range(0, 120).unwrap().collect();
+    let worst = Some(State::Degraded);
+    assert_eq!(rows, vec![(0, worst), (40, worst), (80, worst)]);
+}
+
+#[test]
+fn time_in_states() {
+    let opts = Options::new(0, 60, 600);
+    let mut t = rt::create::in_memory(opts, State::Up).unwrap();
+    let states = [
+        State::Up,
+        State::Up,
+        State::Degraded,
+        State::Up,
+        State::Down,
+        State::Up,
+        State::Up,
+        State::Up,
+    ];
+
+    for (i, s) in states.iter().enumerate() {
+        t.insert(60 * (i as u64 + 1), s).unwrap();
+    }
+
+    let times = t.time_in_states(60, 480).unwrap();
+    assert_eq!(
+        times.states,
+        vec![(State::Up, 360), (State::Degraded, 60), (State::Down, 60)]
+    );
+    assert_eq!(times.unknown, 0);
+    assert_eq!(times.get(State::Down), 60);
+    assert_eq!(times.share(State::Up), Some(0.75));
+
+    let opts = opts.fwd_skip_mode(FwdSkipMode::Unknown);
+    let mut t = rt::create::in_memory(opts, State::Up).unwrap();
+    t.insert(60, &State::Up).unwrap();
+    t.insert(180, &State::Down).unwrap();
+    let times = t.time_in_states(60, 180).unwrap();
+    assert_eq!(times.unknown, 60);
+    assert_eq!(times.known(), 120);
+    assert_eq!(times.share(State::Up), Some(0.5));
+}
@@ -0,0 +1,103 @@
+use roundtable as rt;
+use roundtable::error::Error;
+use roundtable::prelude::*;
+use roundtable::rtdb::{Table, VERSION};
+use std::io::Cursor;
+
+/// Builds a version 1 file with four `u32` slots, updated up to `t = 20`.
+fn legacy() -> Vec<u8> {
+    let mut buf = vec![];
+    buf.extend_from_slice(&0x42445452_u32.to_le_bytes());
+
+    for v in [4, 0_u32.get_hash(), 4, 0, 10, 20] {
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    for v in [1_u32, 2, 3] {
+        buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    buf
+}
+
+#[test]
+fn legacy_files() {
+    let opts = Options::new(0, 10, 40);
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(legacy())).unwrap();
+    assert_eq!(t.version(), 1);
+    assert_eq!(t.schema(), None);
+    assert_eq!(t.first().unwrap(), (0, Some(&1)));
+    assert_eq!(t.last().unwrap(), (20, Some(&3)));
+    t.insert(30, &4).unwrap();
+
+    let buf = t.into_inner().into_inner();
+    assert_eq!(&buf[..4], &0x42445452_u32.to_le_bytes());
+    assert_eq!(buf.len(), legacy().len() + 4);
+    let mut t = Table::load(&opts, &0_u32, Cursor::new(buf)).unwrap();
+    assert_eq!(t.get(30).unwrap(), Some(&4));
+}
+
+#[test]
+fn upgrade() {
+    let opts = Options::new(0, 10, 40);
+    let t = Table::load(&opts, &0_u32, Cursor::new(legacy())).unwrap();
+    let mut t = t.upgrade(&opts, Cursor::new(vec![])).unwrap();
+    assert_eq!(t.version(), VERSION);
+    assert_eq!(t.schema(), Some(&0_u32.get_schema()));
+    t.insert(30, &4).unwrap();
+
+    let mut t = Table::load(&opts, &0_u32, t.into_inner()).unwrap();
+    assert_eq!(t.version(), VERSION);
+    let rows: Vec<_> = t.iter().unwrap().collect();
+    assert_eq!(
+        rows,
+        vec![(0, Some(1)), (10, Some(2)), (20, Some(3)), (30, Some(4))]
+    );
+}
+
+#[test]
+fn upgrade_file() {
+    let path = std::env::temp_dir().join("roundtable-upgrade.rtdb");
+    std::fs::write(&path, legacy()).unwrap();
+
+    let opts = Options::new(0, 10, 40);
+    let mut t = rt::load::upgrade_file::<u32, _>(opts, &path).unwrap();
+    assert_eq!(t.version(), VERSION);
+    assert_eq!(t.last().unwrap(), (20, Some(&3)));
+    drop(t);
+
+    let t = rt::load::upgrade_file::<u32, _>(opts, &path).unwrap();
+    assert_eq!(t.version(), VERSION);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn unsupported() {
+    let opts = Options::new(0, 10, 40);
+    let t = rt::create::in_memory(opts, 0_u32).unwrap();
+    let buf = t.into_inner().into_inner();
+
+    let mut newer = buf.clone();
+    newer[4..8].copy_from_slice(&(VERSION + 1).to_le_bytes());
+    let res = rt::load::from_buffer::<u32, _>(opts, newer);
+    assert_eq!(res.err(), Some(Error::UnsupportedVersion(VERSION + 1)));
+
+    let mut flags = buf;
+    flags[61] |= 0x80;
+    let res = rt::load::from_buffer::<u32, _>(opts, flags);
+    assert_eq!(res.err(), Some(Error::UnsupportedFlags(0x8000)));
+}
+
+#[test]
+fn legacy_hash() {
+    let opts = Options::new(0, 10, 40);
+    let t = rt::create::in_memory(opts, 7_u32).unwrap();
+    let mut buf = t.into_inner().into_inner();
+    buf[60] &= !0x80;
+    let res = rt::load::from_buffer::<u32, _>(opts, buf.clone());
+    assert_eq!(res.err(), Some(Error::InvalidDpHash));
+
+    buf[16..24].copy_from_slice(&0_u32.get_hash().to_le_bytes());
+    let mut t = rt::load::from_buffer::<u32, _>(opts, buf).unwrap();
+    assert_eq!(t.last().unwrap(), (0, Some(&7)));
+}